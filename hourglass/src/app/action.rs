@@ -1,6 +1,9 @@
+#[derive(PartialEq)]
 pub enum Action {
     View,
     Add,
     Update,
     Delete,
+    // typing a `:` command
+    Command,
 }
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
 
-use crate::app::issues::RepoIssue;
+use crate::app::issues::{IssueComment, RepoIssue};
 
 lazy_static! {
     pub static ref ISSUES_CACHE: Mutex<HashMap<&'static str, Vec<RepoIssue>>> =
         Mutex::new(HashMap::new());
+
+    // comments are keyed by issue number
+    pub static ref COMMENTS_CACHE: Mutex<HashMap<u32, Vec<IssueComment>>> =
+        Mutex::new(HashMap::new());
 }
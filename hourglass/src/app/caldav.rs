@@ -0,0 +1,921 @@
+use chrono::{DateTime, Utc};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use url::Url;
+use uuid::Uuid;
+
+use crate::app::config::CalDav;
+use crate::app::formats::{self, ExportOptions, Format};
+use crate::app::task::Task;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDAR_SERVER: &str = "http://calendarserver.org/ns/";
+
+const CTAG_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+  <d:prop><cs:getctag/></d:prop>
+</d:propfind>"#;
+
+// the ETag of every VTODO in the collection, without their content
+const ETAGS_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#;
+
+/// What the last sync saw, to tell local changes from remote ones
+#[derive(Default, Serialize, Deserialize)]
+pub struct SyncState {
+    // changes whenever anything in the collection does, listing it is skipped while it doesn't
+    ctag: Option<String>,
+    items: BTreeMap<Uuid, SyncedItem>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SyncedItem {
+    href: String,
+    etag: String,
+    // the task's `modified_at` when it was last synced, a later one is a local change
+    modified_at: DateTime<Utc>,
+}
+
+impl SyncState {
+    /// An empty state when the file doesn't exist yet, everything is new on the first sync
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|err| format!("unable to read {}: {}", path.display(), err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(err) => Err(format!("unable to read {}: {}", path.display(), err)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let serialized = serde_json::to_string(self).unwrap();
+
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, serialized))
+            .map_err(|err| format!("unable to write {}: {}", path.display(), err))
+    }
+}
+
+/// The local changes a sync asks for, and what happened on the server
+#[derive(Default)]
+pub struct SyncResult {
+    // new or changed on the server, to add or replace going by their uuid
+    pub pulled: Vec<Task>,
+    // deleted on the server
+    pub deleted: Vec<Uuid>,
+    pub pushed: usize,
+    pub deleted_remotely: usize,
+    // edits made on both sides, and how each one was resolved
+    pub conflicts: Vec<String>,
+}
+
+impl SyncResult {
+    pub fn summary(&self) -> String {
+        format!(
+            "synced: {} pulled, {} pushed, {} deleted here, {} deleted on the server, {} conflict(s)",
+            self.pulled.len(),
+            self.pushed,
+            self.deleted.len(),
+            self.deleted_remotely,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// Two-way sync of `local`, the tasks and the archive, with the collection in the config.
+/// When a task was changed on both sides, the latest `modified_at` wins
+pub fn sync(
+    settings: &CalDav,
+    state: &mut SyncState,
+    local: &[&Task],
+) -> Result<SyncResult, String> {
+    sync_with(&CalDavClient::new(settings)?, state, local)
+}
+
+fn sync_with(
+    client: &impl Collection,
+    state: &mut SyncState,
+    local: &[&Task],
+) -> Result<SyncResult, String> {
+    let mut result = SyncResult::default();
+
+    let local: BTreeMap<Uuid, &Task> = local.iter().map(|task| (task.uuid, *task)).collect();
+    // tasks whose server version was taken, they must not be pushed back
+    let mut pulled = BTreeSet::new();
+    // a PUT or DELETE failed because of a change this sync didn't list
+    let mut missed_changes = false;
+
+    let ctag = client.ctag()?;
+
+    // without a ctag there is no telling whether something changed, the collection is listed
+    if ctag.is_none() || ctag != state.ctag {
+        let remote = client.etags()?;
+
+        // deleted on the server
+        for (uuid, item) in state.items.clone() {
+            if remote.contains_key(&item.href) {
+                continue;
+            }
+
+            state.items.remove(&uuid);
+
+            match local.get(&uuid) {
+                // pushed again as a new task below
+                Some(task) if task.modified_at > item.modified_at => {
+                    result.conflicts.push(format!(
+                        "\"{}\" was deleted on the server after being changed here, it was kept",
+                        task.description
+                    ))
+                }
+                Some(_) => result.deleted.push(uuid),
+                None => {}
+            }
+        }
+
+        let hrefs: BTreeMap<&str, &SyncedItem> = state
+            .items
+            .values()
+            .map(|item| (item.href.as_str(), item))
+            .collect();
+
+        let changed: Vec<&str> = remote
+            .iter()
+            .filter(|(href, etag)| {
+                hrefs
+                    .get(href.as_str())
+                    .is_none_or(|item| item.etag != **etag)
+            })
+            .map(|(href, _)| href.as_str())
+            .collect();
+
+        for (href, etag, data) in client.fetch(&changed)? {
+            let tasks = match formats::import(&data, Format::ICalendar) {
+                Ok(tasks) => tasks,
+                Err(err) => {
+                    result
+                        .conflicts
+                        .push(format!("{} was skipped: {}", href, err));
+                    continue;
+                }
+            };
+
+            for task in tasks {
+                let synced = state.items.get(&task.uuid);
+
+                let keep_local = match local.get(&task.uuid) {
+                    Some(local) => {
+                        let changed_here =
+                            synced.is_none_or(|item| local.modified_at > item.modified_at);
+                        let keep_local = changed_here && local.modified_at > task.modified_at;
+
+                        // a task on both sides before its first sync isn't a conflict
+                        if changed_here && synced.is_some() {
+                            result.conflicts.push(format!(
+                                "\"{}\" was changed on both sides, the {} version was kept",
+                                local.description,
+                                if keep_local { "local" } else { "server" }
+                            ));
+                        }
+
+                        keep_local
+                    }
+                    None => {
+                        if synced.is_some() {
+                            result.conflicts.push(format!(
+                                "\"{}\" was changed on the server after being deleted here, it was restored",
+                                task.description
+                            ));
+                        }
+
+                        false
+                    }
+                };
+
+                // when the local version is kept, the older `modified_at` gets it pushed below
+                state.items.insert(
+                    task.uuid,
+                    SyncedItem {
+                        href: href.clone(),
+                        etag: etag.clone(),
+                        modified_at: task.modified_at,
+                    },
+                );
+
+                if !keep_local {
+                    pulled.insert(task.uuid);
+                    result.pulled.push(task);
+                }
+            }
+        }
+    }
+
+    // new and changed here
+    for (uuid, task) in local.iter() {
+        if pulled.contains(uuid) || result.deleted.contains(uuid) {
+            continue;
+        }
+
+        let synced = state.items.get(uuid);
+
+        if synced.is_some_and(|item| task.modified_at <= item.modified_at) {
+            continue;
+        }
+
+        let (href, precondition) = match synced {
+            Some(item) => (item.href.clone(), Precondition::Matches(item.etag.clone())),
+            None => (client.href_for(*uuid)?, Precondition::New),
+        };
+
+        match client.put(&href, task, precondition)? {
+            Some(etag) => {
+                state.items.insert(
+                    *uuid,
+                    SyncedItem {
+                        href,
+                        etag,
+                        modified_at: task.modified_at,
+                    },
+                );
+                result.pushed += 1;
+            }
+            None => {
+                missed_changes = true;
+                result.conflicts.push(format!(
+                    "\"{}\" changed on the server during the sync, it will be synced next time",
+                    task.description
+                ));
+            }
+        }
+    }
+
+    // deleted here
+    for (uuid, item) in state.items.clone() {
+        if local.contains_key(&uuid) || pulled.contains(&uuid) {
+            continue;
+        }
+
+        if client.delete(&item.href, &item.etag)? {
+            state.items.remove(&uuid);
+            result.deleted_remotely += 1;
+        } else {
+            missed_changes = true;
+            result.conflicts.push(format!(
+                "{} changed on the server after being deleted here, it will be synced next time",
+                item.href
+            ));
+        }
+    }
+
+    // after our own changes, so they aren't mistaken for someone else's next time. The changes
+    // that were missed are in the current ctag too, the collection is listed again instead
+    state.ctag = match missed_changes {
+        true => None,
+        false => client.ctag()?,
+    };
+
+    Ok(result)
+}
+
+// what a sync needs from the server, kept in memory by the tests
+trait Collection {
+    fn ctag(&self) -> Result<Option<String>, String>;
+    // href to ETag of every VTODO
+    fn etags(&self) -> Result<BTreeMap<String, String>, String>;
+    // href, ETag and iCalendar data of each resource
+    fn fetch(&self, hrefs: &[&str]) -> Result<Vec<(String, String, String)>, String>;
+    // where a task that isn't on the server yet goes
+    fn href_for(&self, uuid: Uuid) -> Result<String, String>;
+    // the new ETag, `None` when the precondition failed because the server has another version
+    fn put(
+        &self,
+        href: &str,
+        task: &Task,
+        precondition: Precondition,
+    ) -> Result<Option<String>, String>;
+    // false when the precondition failed because the server has another version
+    fn delete(&self, href: &str, etag: &str) -> Result<bool, String>;
+}
+
+enum Precondition {
+    // the resource must not exist yet
+    New,
+    // the resource must still have this ETag
+    Matches(String),
+}
+
+struct CalDavClient {
+    client: Client,
+    // always ends with a `/`, resources are joined to it
+    collection: Url,
+    username: String,
+    password: Option<String>,
+}
+
+impl CalDavClient {
+    fn new(settings: &CalDav) -> Result<Self, String> {
+        if settings.url.is_empty() {
+            return Err(String::from(
+                "no CalDAV collection to sync with, set caldav.url in the config",
+            ));
+        }
+
+        let mut url = settings.url.clone();
+
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+
+        Ok(CalDavClient {
+            client: Client::new(),
+            collection: Url::parse(&url).map_err(|err| err.to_string())?,
+            username: settings.username.clone(),
+            password: env::var("HOURGLASS_CALDAV_PASSWORD").ok(),
+        })
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+
+        if self.username.is_empty() {
+            request
+        } else {
+            request.basic_auth(&self.username, self.password.as_ref())
+        }
+    }
+
+    fn url(&self, href: &str) -> Result<Url, String> {
+        self.collection
+            .join(href)
+            .map_err(|err| format!("invalid href `{}`: {}", href, err))
+    }
+
+    // servers may answer with full URLs or paths, they are compared as paths
+    fn normalize(&self, href: String) -> String {
+        match self.url(&href) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => href,
+        }
+    }
+
+    fn xml_request(&self, method: &str, url: Url, depth: u8, body: &str) -> Result<String, String> {
+        let request = self
+            .request(Method::from_bytes(method.as_bytes()).unwrap(), url.clone())
+            .header("Depth", depth.to_string())
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body.to_string());
+
+        let response = check_status(send(request, method, &url)?, method, &url)?;
+
+        response
+            .text()
+            .map_err(|err| format!("{} {}: {}", method, url, err))
+    }
+}
+
+impl Collection for CalDavClient {
+    fn href_for(&self, uuid: Uuid) -> Result<String, String> {
+        Ok(self.url(&format!("{}.ics", uuid))?.path().to_string())
+    }
+
+    fn ctag(&self) -> Result<Option<String>, String> {
+        let response = self.xml_request("PROPFIND", self.collection.clone(), 0, CTAG_QUERY)?;
+        let document = parse_xml(&response)?;
+
+        let ctag = document
+            .descendants()
+            .find(|node| node.has_tag_name((CALENDAR_SERVER, "getctag")))
+            .and_then(|node| node.text())
+            .map(|ctag| ctag.trim().to_string())
+            .filter(|ctag| !ctag.is_empty());
+
+        Ok(ctag)
+    }
+
+    fn etags(&self) -> Result<BTreeMap<String, String>, String> {
+        let response = self.xml_request("REPORT", self.collection.clone(), 1, ETAGS_QUERY)?;
+
+        Ok(parse_multistatus(&response)?
+            .into_iter()
+            .map(|resource| (self.normalize(resource.href), resource.etag))
+            .filter(|(href, _)| href != self.collection.path())
+            .filter_map(|(href, etag)| Some((href, etag?)))
+            .collect())
+    }
+
+    // in a single calendar-multiget
+    fn fetch(&self, hrefs: &[&str]) -> Result<Vec<(String, String, String)>, String> {
+        if hrefs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let hrefs: String = hrefs
+            .iter()
+            .map(|href| format!("  <d:href>{}</d:href>\n", escape_xml(href)))
+            .collect();
+
+        let query = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+{}</c:calendar-multiget>"#,
+            hrefs
+        );
+
+        let response = self.xml_request("REPORT", self.collection.clone(), 1, &query)?;
+
+        Ok(parse_multistatus(&response)?
+            .into_iter()
+            .filter_map(|resource| {
+                Some((
+                    self.normalize(resource.href),
+                    resource.etag?,
+                    resource.data?,
+                ))
+            })
+            .collect())
+    }
+
+    fn put(
+        &self,
+        href: &str,
+        task: &Task,
+        precondition: Precondition,
+    ) -> Result<Option<String>, String> {
+        let url = self.url(href)?;
+        let body = formats::export(&[task], Format::ICalendar, &ExportOptions::default());
+
+        let request = self
+            .request(Method::PUT, url.clone())
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(body);
+
+        let request = match precondition {
+            Precondition::New => request.header(IF_NONE_MATCH, "*"),
+            Precondition::Matches(etag) => request.header(IF_MATCH, etag),
+        };
+
+        let response = send(request, "PUT", &url)?;
+
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(None);
+        }
+
+        let response = check_status(response, "PUT", &url)?;
+
+        match response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+        {
+            Some(etag) => Ok(Some(etag.to_string())),
+            // some servers change what they store, they send no ETag and it has to be asked for
+            None => Ok(self.etags()?.remove(url.path()).or(Some(String::new()))),
+        }
+    }
+
+    fn delete(&self, href: &str, etag: &str) -> Result<bool, String> {
+        let url = self.url(href)?;
+        let response = send(
+            self.request(Method::DELETE, url.clone())
+                .header(IF_MATCH, etag),
+            "DELETE",
+            &url,
+        )?;
+
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Ok(false),
+            // already gone
+            StatusCode::NOT_FOUND => Ok(true),
+            _ => check_status(response, "DELETE", &url).map(|_| true),
+        }
+    }
+}
+
+fn send(request: RequestBuilder, method: &str, url: &Url) -> Result<Response, String> {
+    request
+        .send()
+        .map_err(|err| format!("{} {}: {}", method, url, err))
+}
+
+fn check_status(response: Response, method: &str, url: &Url) -> Result<Response, String> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(format!("{} {} returned {}", method, url, response.status()))
+    }
+}
+
+// a `<d:response>` of a multistatus
+struct Resource {
+    href: String,
+    etag: Option<String>,
+    data: Option<String>,
+}
+
+fn parse_multistatus(xml: &str) -> Result<Vec<Resource>, String> {
+    let document = parse_xml(xml)?;
+
+    let text = |node: roxmltree::Node, name: (&str, &str)| {
+        node.descendants()
+            .find(|child| child.has_tag_name(name))
+            .and_then(|child| child.text())
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    Ok(document
+        .descendants()
+        .filter(|node| node.has_tag_name((DAV, "response")))
+        .filter_map(|node| {
+            Some(Resource {
+                href: text(node, (DAV, "href"))?,
+                etag: text(node, (DAV, "getetag")),
+                data: text(node, (CALDAV, "calendar-data")),
+            })
+        })
+        .collect())
+}
+
+fn parse_xml(xml: &str) -> Result<roxmltree::Document<'_>, String> {
+    roxmltree::Document::parse(xml)
+        .map_err(|err| format!("invalid response from the server: {}", err))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::cell::RefCell;
+
+    // a collection kept in memory, href to ETag and iCalendar data
+    #[derive(Default)]
+    struct FakeCollection {
+        resources: RefCell<BTreeMap<String, (String, String)>>,
+        // bumped on every change, it is the ETag of the resource that changed and the ctag
+        version: RefCell<u32>,
+        // hrefs someone else changes right before the next PUT or DELETE of the sync
+        races: RefCell<BTreeSet<String>>,
+    }
+
+    impl FakeCollection {
+        fn edit(&self, href: &str, task: &Task) {
+            let data = formats::export(&[task], Format::ICalendar, &ExportOptions::default());
+            let etag = self.bump();
+
+            self.resources
+                .borrow_mut()
+                .insert(href.to_string(), (etag, data));
+        }
+
+        fn remove(&self, href: &str) {
+            self.bump();
+            self.resources.borrow_mut().remove(href);
+        }
+
+        fn task(&self, href: &str) -> Task {
+            let data = self.resources.borrow()[href].1.clone();
+
+            formats::import(&data, Format::ICalendar).unwrap().remove(0)
+        }
+
+        fn bump(&self) -> String {
+            *self.version.borrow_mut() += 1;
+
+            format!("\"{}\"", self.version.borrow())
+        }
+
+        fn race(&self, href: &str) {
+            if self.races.borrow_mut().remove(href) {
+                let mut task = self.task(href);
+                task.description.push_str(" (changed)");
+                self.edit(href, &task);
+            }
+        }
+
+        fn etag(&self, href: &str) -> Option<String> {
+            self.resources
+                .borrow()
+                .get(href)
+                .map(|(etag, _)| etag.clone())
+        }
+    }
+
+    impl Collection for FakeCollection {
+        fn ctag(&self) -> Result<Option<String>, String> {
+            Ok(Some(self.version.borrow().to_string()))
+        }
+
+        fn etags(&self) -> Result<BTreeMap<String, String>, String> {
+            Ok(self
+                .resources
+                .borrow()
+                .iter()
+                .map(|(href, (etag, _))| (href.clone(), etag.clone()))
+                .collect())
+        }
+
+        fn fetch(&self, hrefs: &[&str]) -> Result<Vec<(String, String, String)>, String> {
+            let resources = self.resources.borrow();
+
+            Ok(hrefs
+                .iter()
+                .filter_map(|href| {
+                    let (etag, data) = resources.get(*href)?;
+                    Some((href.to_string(), etag.clone(), data.clone()))
+                })
+                .collect())
+        }
+
+        fn href_for(&self, uuid: Uuid) -> Result<String, String> {
+            Ok(format!("/tasks/{}.ics", uuid))
+        }
+
+        fn put(
+            &self,
+            href: &str,
+            task: &Task,
+            precondition: Precondition,
+        ) -> Result<Option<String>, String> {
+            self.race(href);
+
+            let matches = match precondition {
+                Precondition::New => self.etag(href).is_none(),
+                Precondition::Matches(etag) => self.etag(href) == Some(etag),
+            };
+
+            if !matches {
+                return Ok(None);
+            }
+
+            self.edit(href, task);
+
+            Ok(self.etag(href))
+        }
+
+        fn delete(&self, href: &str, etag: &str) -> Result<bool, String> {
+            self.race(href);
+
+            match self.etag(href) {
+                Some(current) if current != etag => Ok(false),
+                _ => {
+                    self.remove(href);
+                    Ok(true)
+                }
+            }
+        }
+    }
+
+    fn task(description: &str, modified_minutes: i64) -> Task {
+        let mut task = Task::new(1, Default::default());
+        task.description = description.to_string();
+        task.created_at = Utc.with_ymd_and_hms(2023, 6, 1, 9, 0, 0).unwrap();
+        task.modified_at = task.created_at + chrono::Duration::minutes(modified_minutes);
+
+        task
+    }
+
+    fn href(task: &Task) -> String {
+        format!("/tasks/{}.ics", task.uuid)
+    }
+
+    // a task synced once, with nothing left to do
+    fn synced(description: &str) -> (FakeCollection, SyncState, Task) {
+        let server = FakeCollection::default();
+        let mut state = SyncState::default();
+        let task = task(description, 0);
+
+        let result = sync_with(&server, &mut state, &[&task]).unwrap();
+        assert_eq!(result.pushed, 1);
+
+        (server, state, task)
+    }
+
+    #[test]
+    fn syncs_new_tasks_both_ways() {
+        let server = FakeCollection::default();
+        let mut state = SyncState::default();
+        let local = task("Local", 0);
+        let remote = task("Remote", 0);
+        server.edit("/tasks/remote.ics", &remote);
+
+        let result = sync_with(&server, &mut state, &[&local]).unwrap();
+
+        assert_eq!(result.pushed, 1);
+        assert_eq!(result.pulled.len(), 1);
+        assert_eq!(result.pulled[0].uuid, remote.uuid);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(server.task(&href(&local)).description, "Local");
+
+        let result = sync_with(&server, &mut state, &[&local, &result.pulled[0]]).unwrap();
+
+        assert_eq!(
+            result.summary(),
+            "synced: 0 pulled, 0 pushed, 0 deleted here, 0 deleted on the server, 0 conflict(s)"
+        );
+    }
+
+    #[test]
+    fn pushes_and_pulls_changes() {
+        let (server, mut state, local) = synced("Task");
+        let mut changed = local.clone();
+        changed.description = String::from("Changed here");
+        changed.modified_at += chrono::Duration::minutes(1);
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert_eq!(result.pushed, 1);
+        assert_eq!(server.task(&href(&local)).description, "Changed here");
+
+        let mut remote = changed.clone();
+        remote.description = String::from("Changed there");
+        remote.modified_at += chrono::Duration::minutes(1);
+        server.edit(&href(&local), &remote);
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert_eq!(result.pushed, 0);
+        assert_eq!(result.pulled[0].description, "Changed there");
+        assert!(result.conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_latest_change_made_on_both_sides() {
+        let (server, mut state, local) = synced("Task");
+        let mut remote = local.clone();
+        remote.description = String::from("Changed there");
+        remote.modified_at += chrono::Duration::minutes(1);
+        server.edit(&href(&local), &remote);
+        let mut changed = local.clone();
+        changed.description = String::from("Changed here");
+        changed.modified_at += chrono::Duration::minutes(2);
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert!(result.pulled.is_empty());
+        assert_eq!(result.pushed, 1);
+        assert_eq!(
+            result.conflicts,
+            ["\"Changed here\" was changed on both sides, the local version was kept"]
+        );
+        assert_eq!(server.task(&href(&local)).description, "Changed here");
+
+        remote.modified_at += chrono::Duration::minutes(5);
+        server.edit(&href(&local), &remote);
+        changed.modified_at += chrono::Duration::minutes(1);
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert_eq!(result.pushed, 0);
+        assert_eq!(result.pulled[0].description, "Changed there");
+        assert_eq!(
+            result.conflicts,
+            ["\"Changed here\" was changed on both sides, the server version was kept"]
+        );
+    }
+
+    #[test]
+    fn deletes_tasks_deleted_on_the_server() {
+        let (server, mut state, local) = synced("Task");
+        server.remove(&href(&local));
+
+        let result = sync_with(&server, &mut state, &[&local]).unwrap();
+
+        assert_eq!(result.deleted, [local.uuid]);
+        assert_eq!(result.pushed, 0);
+        assert!(state.items.is_empty());
+    }
+
+    #[test]
+    fn keeps_tasks_changed_here_after_being_deleted_on_the_server() {
+        let (server, mut state, local) = synced("Task");
+        server.remove(&href(&local));
+        let mut changed = local.clone();
+        changed.modified_at += chrono::Duration::minutes(1);
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert!(result.deleted.is_empty());
+        assert_eq!(result.pushed, 1);
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(server.etag(&href(&local)).is_some());
+    }
+
+    #[test]
+    fn deletes_on_the_server_tasks_deleted_here() {
+        let (server, mut state, local) = synced("Task");
+
+        let result = sync_with(&server, &mut state, &[]).unwrap();
+
+        assert_eq!(result.deleted_remotely, 1);
+        assert!(server.etag(&href(&local)).is_none());
+        assert!(state.items.is_empty());
+    }
+
+    #[test]
+    fn restores_tasks_changed_on_the_server_after_being_deleted_here() {
+        let (server, mut state, local) = synced("Task");
+        let mut remote = local.clone();
+        remote.modified_at += chrono::Duration::minutes(1);
+        server.edit(&href(&local), &remote);
+
+        let result = sync_with(&server, &mut state, &[]).unwrap();
+
+        assert_eq!(result.deleted_remotely, 0);
+        assert_eq!(result.pulled.len(), 1);
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(server.etag(&href(&local)).is_some());
+    }
+
+    #[test]
+    fn retries_changes_whose_precondition_failed() {
+        let (server, mut state, local) = synced("Task");
+        let mut changed = local.clone();
+        changed.modified_at += chrono::Duration::minutes(10);
+        server.races.borrow_mut().insert(href(&local));
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert_eq!(result.pushed, 0);
+        assert_eq!(
+            result.conflicts,
+            ["\"Task\" changed on the server during the sync, it will be synced next time"]
+        );
+        assert_eq!(server.task(&href(&local)).description, "Task (changed)");
+
+        let result = sync_with(&server, &mut state, &[&changed]).unwrap();
+
+        assert_eq!(result.pushed, 1);
+        assert_eq!(server.task(&href(&local)).description, "Task");
+    }
+
+    #[test]
+    fn keeps_deletions_whose_precondition_failed() {
+        let (server, mut state, local) = synced("Task");
+        server.races.borrow_mut().insert(href(&local));
+
+        let result = sync_with(&server, &mut state, &[]).unwrap();
+
+        assert_eq!(result.deleted_remotely, 0);
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(state.items.contains_key(&local.uuid));
+
+        let result = sync_with(&server, &mut state, &[]).unwrap();
+
+        assert_eq!(result.pulled[0].description, "Task (changed)");
+    }
+
+    #[test]
+    fn parses_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<multistatus xmlns="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <response>
+    <href> /tasks/ </href>
+    <propstat><prop><getetag/></prop></propstat>
+  </response>
+  <response>
+    <href>/tasks/a.ics</href>
+    <propstat>
+      <prop>
+        <getetag>"1"</getetag>
+        <C:calendar-data>BEGIN:VCALENDAR
+END:VCALENDAR</C:calendar-data>
+      </prop>
+    </propstat>
+  </response>
+  <response><propstat><prop><getetag>"2"</getetag></prop></propstat></response>
+</multistatus>"#;
+
+        let resources = parse_multistatus(xml).unwrap();
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].href, "/tasks/");
+        assert_eq!(resources[0].etag, None);
+        assert_eq!(resources[1].href, "/tasks/a.ics");
+        assert_eq!(resources[1].etag.as_deref(), Some("\"1\""));
+        assert_eq!(
+            resources[1].data.as_deref(),
+            Some("BEGIN:VCALENDAR\nEND:VCALENDAR")
+        );
+        assert!(parse_multistatus("<multistatus>").is_err());
+    }
+}
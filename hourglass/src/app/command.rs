@@ -0,0 +1,682 @@
+use chrono::{DateTime, Local, Utc};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::app::formats::Format;
+use crate::app::storage::Scope;
+use crate::app::task::{Priority, Task};
+use crate::util::parse_date;
+
+/// Names of the `:` commands, also offered by the command bar completion
+pub const COMMANDS: [&str; 22] = [
+    "add", "done", "undone", "toggle", "delete", "modify", "move", "undo", "archive", "restore",
+    "purge", "sort", "filter", "scope", "tab", "list", "export", "import", "sync", "w", "q", "wq",
+];
+
+/// Everything that can be done to the task list, either typed after `:` or bound to a key
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Add(String),
+    Done(Selection),
+    Undone(Selection),
+    Toggle(Selection),
+    Delete(Selection),
+    Modify(Selection, Modification),
+    // to another list, by its name
+    Move(Selection, String),
+    Undo,
+    // moves every completed task to the archive, whatever its age
+    Archive,
+    Restore(Selection),
+    Purge(Selection),
+    Sort(Option<SortKey>),
+    Filter(Option<Filter>),
+    // which stores the tasks tab shows
+    Scope(Scope),
+    Tab(String),
+    // goes to the tab of a named list, creating the list when there is none
+    List(String),
+    Export(Format, PathBuf),
+    Import(Format, PathBuf),
+    // two-way sync with the CalDAV collection of the config
+    Sync,
+    Write,
+    Quit,
+    WriteQuit,
+}
+
+/// Which tasks a command applies to
+#[derive(Debug, PartialEq)]
+pub enum Selection {
+    // the row under the cursor
+    Current,
+    // task ids, e.g. `3-7 9`
+    Ids(Vec<RangeInclusive<i32>>),
+}
+
+impl Selection {
+    pub fn contains(&self, id: i32) -> bool {
+        match self {
+            Selection::Current => false,
+            Selection::Ids(ranges) => ranges.iter().any(|range| range.contains(&id)),
+        }
+    }
+}
+
+/// Attribute changes applied to every selected task, e.g. `+urgent -later priority:H due:`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Modification {
+    pub added_tags: Vec<String>,
+    pub removed_tags: Vec<String>,
+    // `Some(None)` when the attribute is cleared with an empty value, like `project:`
+    pub project: Option<Option<String>>,
+    pub priority: Option<Option<Priority>>,
+    pub due: Option<Option<DateTime<Utc>>>,
+}
+
+impl Modification {
+    pub fn parse(args: &[&str]) -> Result<Self, ParseError> {
+        let mut modification = Modification::default();
+
+        for arg in args {
+            let invalid = || ParseError::InvalidValue(arg.to_string());
+
+            if let Some(tag) = arg.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+                modification.added_tags.push(tag.to_string());
+            } else if let Some(tag) = arg.strip_prefix('-').filter(|tag| !tag.is_empty()) {
+                modification.removed_tags.push(tag.to_string());
+            } else if let Some(project) = arg.strip_prefix("project:") {
+                modification.project = Some(Some(project.to_string()).filter(|p| !p.is_empty()));
+            } else if let Some(priority) = arg.strip_prefix("priority:") {
+                modification.priority = match priority {
+                    "" => Some(None),
+                    priority => Some(Some(Priority::parse(priority).ok_or_else(invalid)?)),
+                };
+            } else if let Some(due) = arg.strip_prefix("due:") {
+                modification.due = match due {
+                    "" => Some(None),
+                    due => Some(Some(parse_date(due, Local::now()).ok_or_else(invalid)?)),
+                };
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        if modification == Modification::default() {
+            return Err(ParseError::MissingArgument(
+                "modify",
+                "+tag, -tag, project:, priority: or due:",
+            ));
+        }
+
+        Ok(modification)
+    }
+
+    pub fn apply(&self, task: &mut Task) {
+        for tag in self.added_tags.iter() {
+            if !task.tags.contains(tag) {
+                task.tags.push(tag.clone());
+            }
+        }
+
+        task.tags.retain(|tag| !self.removed_tags.contains(tag));
+
+        if let Some(project) = &self.project {
+            task.project = project.clone();
+        }
+
+        if let Some(priority) = self.priority {
+            task.priority = priority;
+        }
+
+        if let Some(due) = self.due {
+            task.due = due;
+        }
+
+        task.modified_at = Utc::now();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Id,
+    Description,
+    Project,
+    Priority,
+    Due,
+    Created,
+    Modified,
+}
+
+impl SortKey {
+    pub const NAMES: [&'static str; 7] = [
+        "id",
+        "description",
+        "project",
+        "priority",
+        "due",
+        "created",
+        "modified",
+    ];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "id" => Some(SortKey::Id),
+            "description" | "desc" => Some(SortKey::Description),
+            "project" => Some(SortKey::Project),
+            "priority" => Some(SortKey::Priority),
+            "due" => Some(SortKey::Due),
+            "created" | "age" => Some(SortKey::Created),
+            "modified" => Some(SortKey::Modified),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortKey::Id => "id",
+            SortKey::Description => "description",
+            SortKey::Project => "project",
+            SortKey::Priority => "priority",
+            SortKey::Due => "due",
+            SortKey::Created => "created",
+            SortKey::Modified => "modified",
+        }
+    }
+
+    pub fn compare(&self, a: &Task, b: &Task) -> std::cmp::Ordering {
+        // tasks without a value go last
+        fn some_first<T: Ord>(a: &Option<T>, b: &Option<T>) -> std::cmp::Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        }
+
+        match self {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Description => a
+                .description
+                .to_lowercase()
+                .cmp(&b.description.to_lowercase()),
+            SortKey::Project => some_first(&a.project, &b.project),
+            SortKey::Priority => some_first(&a.priority, &b.priority),
+            SortKey::Due => some_first(&a.due, &b.due),
+            SortKey::Created => a.created_at.cmp(&b.created_at),
+            SortKey::Modified => a.modified_at.cmp(&b.modified_at),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Pending,
+    Completed,
+}
+
+/// Narrows the task table down, every condition has to match
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub excluded_tags: Vec<String>,
+    pub project: Option<String>,
+    pub priority: Option<Priority>,
+    pub status: Option<Status>,
+    // matched against the description, case insensitive
+    pub words: Vec<String>,
+    // the text the filter was parsed from, shown in the UI
+    pub source: String,
+}
+
+impl Filter {
+    pub fn parse(args: &[&str]) -> Result<Self, ParseError> {
+        let mut filter = Filter {
+            source: args.join(" "),
+            ..Filter::default()
+        };
+
+        for arg in args {
+            if let Some(tag) = arg.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+                filter.tags.push(tag.to_string());
+            } else if let Some(tag) = arg.strip_prefix('-').filter(|tag| !tag.is_empty()) {
+                filter.excluded_tags.push(tag.to_string());
+            } else if let Some(project) = arg.strip_prefix("project:") {
+                filter.project = Some(project.to_string());
+            } else if let Some(priority) = arg.strip_prefix("priority:") {
+                filter.priority = Some(
+                    Priority::parse(priority)
+                        .ok_or_else(|| ParseError::InvalidValue(arg.to_string()))?,
+                );
+            } else if let Some(status) = arg.strip_prefix("status:") {
+                filter.status = match status {
+                    "pending" => Some(Status::Pending),
+                    "completed" | "done" => Some(Status::Completed),
+                    _ => return Err(ParseError::InvalidValue(arg.to_string())),
+                };
+            } else {
+                filter.words.push(arg.to_lowercase());
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        let description = task.description.to_lowercase();
+
+        self.tags.iter().all(|tag| task.tags.contains(tag))
+            && !self.excluded_tags.iter().any(|tag| task.tags.contains(tag))
+            && self
+                .project
+                .as_ref()
+                .is_none_or(|project| task.project.as_ref() == Some(project))
+            && self
+                .priority
+                .is_none_or(|priority| task.priority == Some(priority))
+            && self.status.is_none_or(|status| match status {
+                Status::Pending => !task.completed,
+                Status::Completed => task.completed,
+            })
+            && self.words.iter().all(|word| description.contains(word))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand(String),
+    MissingArgument(&'static str, &'static str),
+    TooManyArguments(&'static str),
+    InvalidId(String),
+    InvalidValue(String),
+    UnknownSortKey(String),
+    UnknownFormat(String),
+    // the argument from the opening quote on
+    UnclosedQuote(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "no command given"),
+            ParseError::UnknownCommand(name) => write!(f, "unknown command `{}`", name),
+            ParseError::MissingArgument(command, argument) => {
+                write!(f, "`{}` expects {}", command, argument)
+            }
+            // the likely reason is a path with spaces
+            ParseError::TooManyArguments(command @ ("export" | "import")) => write!(
+                f,
+                "too many arguments for `{}`, put a path with spaces in quotes",
+                command
+            ),
+            ParseError::TooManyArguments(command) => {
+                write!(f, "too many arguments for `{}`", command)
+            }
+            ParseError::InvalidId(id) => {
+                write!(f, "`{}` is not a task id or a range like `3-7`", id)
+            }
+            ParseError::InvalidValue(value) => write!(f, "invalid value `{}`", value),
+            ParseError::UnknownSortKey(key) => write!(
+                f,
+                "cannot sort by `{}`, expected one of {}",
+                key,
+                SortKey::NAMES.join(", ")
+            ),
+            ParseError::UnknownFormat(format) => write!(
+                f,
+                "unknown format `{}`, expected one of {}",
+                format,
+                Format::NAMES.join(", ")
+            ),
+            ParseError::UnclosedQuote(arg) => write!(f, "missing the closing quote of `{}`", arg),
+        }
+    }
+}
+
+/// Parses what was typed after `:`, the leading `:` is optional
+pub fn parse(input: &str) -> Result<Command, ParseError> {
+    let input = input.trim().trim_start_matches(':');
+
+    let (name, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+    if name.is_empty() {
+        return Err(ParseError::Empty);
+    }
+
+    // a description is taken as typed, quotes included
+    if let "add" | "a" = name {
+        return match rest.trim() {
+            "" => Err(ParseError::MissingArgument("add", "a description")),
+            description => Ok(Command::Add(description.to_string())),
+        };
+    }
+
+    let args = split_args(rest)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match name {
+        "done" => Ok(Command::Done(parse_selection(&args)?)),
+        "undone" => Ok(Command::Undone(parse_selection(&args)?)),
+        "toggle" => Ok(Command::Toggle(parse_selection(&args)?)),
+        "delete" | "del" => Ok(Command::Delete(parse_selection(&args)?)),
+        // ids come first, the first word that isn't one starts the changes
+        "modify" | "mod" => {
+            let ids = args
+                .iter()
+                .take_while(|arg| arg.starts_with(|c: char| c.is_ascii_digit()))
+                .count();
+
+            Ok(Command::Modify(
+                parse_selection(&args[..ids])?,
+                Modification::parse(&args[ids..])?,
+            ))
+        }
+        // the list comes last, everything before it is ids
+        "move" => match args.split_last() {
+            None => Err(ParseError::MissingArgument("move", "a list name")),
+            Some((list, ids)) => Ok(Command::Move(parse_selection(ids)?, list.to_string())),
+        },
+        "undo" | "u" => no_arguments("undo", &args, Command::Undo),
+        "archive" => no_arguments("archive", &args, Command::Archive),
+        "restore" => Ok(Command::Restore(parse_selection(&args)?)),
+        "purge" => Ok(Command::Purge(parse_selection(&args)?)),
+        "sort" => match args.as_slice() {
+            // without a sort key the tasks are in their manual order
+            [] | ["none"] | ["manual"] => Ok(Command::Sort(None)),
+            [key] => SortKey::parse(key)
+                .map(|key| Command::Sort(Some(key)))
+                .ok_or_else(|| ParseError::UnknownSortKey(key.to_string())),
+            _ => Err(ParseError::TooManyArguments("sort")),
+        },
+        "filter" => {
+            if args.is_empty() {
+                Ok(Command::Filter(None))
+            } else {
+                Ok(Command::Filter(Some(Filter::parse(&args)?)))
+            }
+        }
+        "scope" => match args.as_slice() {
+            [] => Err(ParseError::MissingArgument("scope", "repo, global or all")),
+            [scope] => Scope::parse(scope)
+                .map(Command::Scope)
+                .ok_or_else(|| ParseError::InvalidValue(scope.to_string())),
+            _ => Err(ParseError::TooManyArguments("scope")),
+        },
+        "tab" => match args.as_slice() {
+            [] => Err(ParseError::MissingArgument("tab", "a tab name")),
+            [tab] => Ok(Command::Tab(tab.to_string())),
+            _ => Err(ParseError::TooManyArguments("tab")),
+        },
+        "list" => match args.as_slice() {
+            [] => Err(ParseError::MissingArgument("list", "a list name")),
+            [name] => Ok(Command::List(name.to_string())),
+            _ => Err(ParseError::TooManyArguments("list")),
+        },
+        "export" => match args.as_slice() {
+            [] => Err(ParseError::MissingArgument("export", "a format and a file")),
+            [_] => Err(ParseError::MissingArgument("export", "a file")),
+            [format, path] => Format::parse(format)
+                .map(|format| Command::Export(format, PathBuf::from(path)))
+                .ok_or_else(|| ParseError::UnknownFormat(format.to_string())),
+            _ => Err(ParseError::TooManyArguments("export")),
+        },
+        "import" => match args.as_slice() {
+            [] => Err(ParseError::MissingArgument("import", "a format and a file")),
+            [_] => Err(ParseError::MissingArgument("import", "a file")),
+            [format, path] => Format::parse(format)
+                .map(|format| Command::Import(format, PathBuf::from(path)))
+                .ok_or_else(|| ParseError::UnknownFormat(format.to_string())),
+            _ => Err(ParseError::TooManyArguments("import")),
+        },
+        "sync" => no_arguments("sync", &args, Command::Sync),
+        "w" | "write" => no_arguments("w", &args, Command::Write),
+        "q" | "quit" => no_arguments("q", &args, Command::Quit),
+        "wq" | "x" => no_arguments("wq", &args, Command::WriteQuit),
+        other => Err(ParseError::UnknownCommand(other.to_string())),
+    }
+}
+
+fn no_arguments(
+    name: &'static str,
+    args: &[&str],
+    command: Command,
+) -> Result<Command, ParseError> {
+    if args.is_empty() {
+        Ok(command)
+    } else {
+        Err(ParseError::TooManyArguments(name))
+    }
+}
+
+// words separated by whitespace, one in double or single quotes can have spaces in it,
+// e.g. `:export csv "my tasks.csv"`
+fn split_args(input: &str) -> Result<Vec<String>, ParseError> {
+    let mut args = vec![];
+    let mut rest = input.trim_start();
+
+    while let Some(first) = rest.chars().next() {
+        let (arg, tail) = match first {
+            '"' | '\'' => {
+                let end = rest[1..]
+                    .find(first)
+                    .ok_or_else(|| ParseError::UnclosedQuote(rest.to_string()))?;
+
+                (&rest[1..end + 1], &rest[end + 2..])
+            }
+            _ => rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len())),
+        };
+
+        args.push(arg.to_string());
+        rest = tail.trim_start();
+    }
+
+    Ok(args)
+}
+
+// `3`, `3-7`, `3,5,9-12` or several of them separated by spaces
+fn parse_selection(args: &[&str]) -> Result<Selection, ParseError> {
+    if args.is_empty() {
+        return Ok(Selection::Current);
+    }
+
+    let mut ranges = vec![];
+
+    for part in args.iter().flat_map(|arg| arg.split(',')) {
+        if part.is_empty() {
+            continue;
+        }
+
+        let invalid = || ParseError::InvalidId(part.to_string());
+
+        let range = match part.split_once('-') {
+            Some((start, end)) => {
+                let start: i32 = start.parse().map_err(|_| invalid())?;
+                let end: i32 = end.parse().map_err(|_| invalid())?;
+
+                if start > end {
+                    return Err(invalid());
+                }
+
+                start..=end
+            }
+            None => {
+                let id: i32 = part.parse().map_err(|_| invalid())?;
+
+                id..=id
+            }
+        };
+
+        ranges.push(range);
+    }
+
+    Ok(Selection::Ids(ranges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ranges: &[RangeInclusive<i32>]) -> Selection {
+        Selection::Ids(ranges.to_vec())
+    }
+
+    #[test]
+    fn selections() {
+        assert_eq!(parse(":done"), Ok(Command::Done(Selection::Current)));
+        assert_eq!(parse("done 4"), Ok(Command::Done(ids(&[4..=4]))));
+        assert_eq!(
+            parse(":delete 3-7 9,11-12"),
+            Ok(Command::Delete(ids(&[3..=7, 9..=9, 11..=12])))
+        );
+        assert!(ids(&[3..=7]).contains(5));
+        assert!(!ids(&[3..=7]).contains(8));
+        assert!(!Selection::Current.contains(1));
+    }
+
+    #[test]
+    fn invalid_ids() {
+        for (input, id) in [
+            ("done x", "x"),
+            ("done 7-3", "7-3"),
+            ("done 3-", "3-"),
+            ("delete 1,a", "a"),
+            ("toggle -2", "-2"),
+        ] {
+            assert_eq!(parse(input), Err(ParseError::InvalidId(id.to_string())));
+        }
+    }
+
+    #[test]
+    fn unknown_commands() {
+        assert_eq!(parse(":"), Err(ParseError::Empty));
+        assert_eq!(parse("   "), Err(ParseError::Empty));
+        assert_eq!(
+            parse(":frobnicate 3"),
+            Err(ParseError::UnknownCommand(String::from("frobnicate")))
+        );
+    }
+
+    #[test]
+    fn missing_arguments() {
+        assert_eq!(
+            parse(":add "),
+            Err(ParseError::MissingArgument("add", "a description"))
+        );
+        assert_eq!(
+            parse(":tab"),
+            Err(ParseError::MissingArgument("tab", "a tab name"))
+        );
+        assert_eq!(
+            parse(":export csv"),
+            Err(ParseError::MissingArgument("export", "a file"))
+        );
+        assert!(matches!(
+            parse(":modify 3"),
+            Err(ParseError::MissingArgument("modify", _))
+        ));
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        assert_eq!(parse(":w now"), Err(ParseError::TooManyArguments("w")));
+        assert_eq!(
+            parse(":sort due id"),
+            Err(ParseError::TooManyArguments("sort"))
+        );
+        assert_eq!(
+            parse(":export csv my tasks.csv"),
+            Err(ParseError::TooManyArguments("export"))
+        );
+        assert!(ParseError::TooManyArguments("export")
+            .to_string()
+            .contains("quotes"));
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            parse(":sort size"),
+            Err(ParseError::UnknownSortKey(String::from("size")))
+        );
+        assert_eq!(
+            parse(":export pdf out.pdf"),
+            Err(ParseError::UnknownFormat(String::from("pdf")))
+        );
+        assert_eq!(
+            parse(":modify 3 priority:X"),
+            Err(ParseError::InvalidValue(String::from("priority:X")))
+        );
+        assert_eq!(
+            parse(":filter status:maybe"),
+            Err(ParseError::InvalidValue(String::from("status:maybe")))
+        );
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            parse(":export csv out.csv"),
+            Ok(Command::Export(Format::Csv, PathBuf::from("out.csv")))
+        );
+        assert_eq!(
+            parse(":import todotxt \"my tasks/todo.txt\""),
+            Ok(Command::Import(
+                Format::TodoTxt,
+                PathBuf::from("my tasks/todo.txt")
+            ))
+        );
+        assert_eq!(
+            parse(":export md 'a b.md'"),
+            Ok(Command::Export(Format::Markdown, PathBuf::from("a b.md")))
+        );
+        assert_eq!(
+            parse(":export md \"a b.md"),
+            Err(ParseError::UnclosedQuote(String::from("\"a b.md")))
+        );
+    }
+
+    #[test]
+    fn descriptions_are_taken_as_typed() {
+        assert_eq!(
+            parse(":add say \"hi\" to  Bob's +team"),
+            Ok(Command::Add(String::from("say \"hi\" to  Bob's +team")))
+        );
+    }
+
+    #[test]
+    fn modify_and_move() {
+        let modification = Modification {
+            added_tags: vec![String::from("urgent")],
+            removed_tags: vec![String::from("later")],
+            project: Some(None),
+            ..Modification::default()
+        };
+
+        assert_eq!(
+            parse(":modify 3-4 +urgent -later project:"),
+            Ok(Command::Modify(ids(&[3..=4]), modification))
+        );
+        assert_eq!(
+            parse(":move 2 work"),
+            Ok(Command::Move(ids(&[2..=2]), String::from("work")))
+        );
+        assert_eq!(
+            parse(":move work"),
+            Ok(Command::Move(Selection::Current, String::from("work")))
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let filter = Filter::parse(&["+bug", "-wontfix", "priority:h", "Crash"]).unwrap();
+
+        assert_eq!(filter.tags, ["bug"]);
+        assert_eq!(filter.excluded_tags, ["wontfix"]);
+        assert_eq!(filter.priority, Some(Priority::High));
+        assert_eq!(filter.words, ["crash"]);
+        assert_eq!(parse(":filter"), Ok(Command::Filter(None)));
+    }
+}
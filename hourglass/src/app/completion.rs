@@ -0,0 +1,165 @@
+use std::collections::BTreeSet;
+
+use crate::app::command::{SortKey, COMMANDS};
+use crate::app::formats::Format;
+use crate::app::issues::RepoIssue;
+use crate::app::storage::Scope;
+use crate::app::task::{Task, ATTRIBUTES};
+use crate::util::DATE_KEYWORDS;
+
+const PRIORITIES: [&str; 3] = ["H", "M", "L"];
+
+pub struct Candidate {
+    // what replaces the word under the cursor
+    pub replacement: String,
+    // what is shown in the popup
+    pub display: String,
+}
+
+/// The completion popup shown above the command bar
+pub struct Completion {
+    // byte offset in the command input where the completed word starts
+    pub start: usize,
+    pub candidates: Vec<Candidate>,
+    pub selected: usize,
+}
+
+impl Completion {
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.candidates.len();
+    }
+
+    pub fn previous(&mut self) {
+        if self.selected == 0 {
+            self.selected = self.candidates.len() - 1;
+        } else {
+            self.selected -= 1;
+        }
+    }
+
+    pub fn selected(&self) -> &Candidate {
+        &self.candidates[self.selected]
+    }
+}
+
+/// Finds completions for the word that ends at `pos` in `line`
+pub fn complete(
+    line: &str,
+    pos: usize,
+    tasks: &[Task],
+    issues: &[RepoIssue],
+    tabs: &[String],
+    lists: &[String],
+) -> Option<Completion> {
+    let start = line[..pos]
+        .rfind(char::is_whitespace)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let word = &line[start..pos];
+
+    // the first word of a `:` command, what comes after it depends on the command
+    let command = line
+        .strip_prefix(':')
+        .and_then(|rest| rest.split_whitespace().next())
+        .filter(|_| start > 0);
+
+    let candidates: Vec<Candidate> = if start == 0 && word.starts_with(':') {
+        COMMANDS
+            .iter()
+            .filter(|command| command.starts_with(&word[1..]))
+            .map(|command| candidate(format!(":{}", command)))
+            .collect()
+    } else if command == Some("sort") {
+        let mut keys = SortKey::NAMES.to_vec();
+        keys.push("manual");
+
+        complete_from(&keys, word)
+    } else if command == Some("scope") {
+        complete_from(&Scope::NAMES, word)
+    } else if command == Some("tab") {
+        let tabs: Vec<&str> = tabs.iter().map(String::as_str).collect();
+
+        complete_from(&tabs, word)
+    } else if matches!(command, Some("list" | "move")) {
+        let lists: Vec<&str> = lists.iter().map(String::as_str).collect();
+
+        complete_from(&lists, word)
+    } else if matches!(command, Some("export" | "import"))
+        && line[..start].split_whitespace().count() == 1
+    {
+        complete_from(&Format::NAMES, word)
+    } else if let Some(prefix) = word.strip_prefix('+') {
+        let tags: BTreeSet<&String> = tasks.iter().flat_map(|task| task.tags.iter()).collect();
+
+        tags.into_iter()
+            .filter(|tag| tag.starts_with(prefix))
+            .map(|tag| candidate(format!("+{}", tag)))
+            .collect()
+    } else if let Some(prefix) = word.strip_prefix("project:") {
+        let projects: BTreeSet<&String> = tasks
+            .iter()
+            .filter_map(|task| task.project.as_ref())
+            .collect();
+
+        projects
+            .into_iter()
+            .filter(|project| project.starts_with(prefix))
+            .map(|project| candidate(format!("project:{}", project)))
+            .collect()
+    } else if let Some(prefix) = word.strip_prefix("due:") {
+        DATE_KEYWORDS
+            .iter()
+            .filter(|keyword| keyword.starts_with(prefix))
+            .map(|keyword| candidate(format!("due:{}", keyword)))
+            .collect()
+    } else if let Some(prefix) = word.strip_prefix("priority:") {
+        PRIORITIES
+            .iter()
+            .filter(|priority| priority.starts_with(&prefix.to_uppercase()))
+            .map(|priority| candidate(format!("priority:{}", priority)))
+            .collect()
+    } else if let Some(prefix) = word.strip_prefix('#') {
+        issues
+            .iter()
+            .filter(|issue| issue.number.to_string().starts_with(prefix))
+            .map(|issue| Candidate {
+                replacement: format!("#{}", issue.number),
+                display: format!("#{} {}", issue.number, issue.title),
+            })
+            .collect()
+    } else if !word.is_empty() {
+        ATTRIBUTES
+            .iter()
+            .filter(|attribute| attribute.starts_with(word))
+            .map(|attribute| candidate(attribute.to_string()))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    if candidates.is_empty() {
+        return None;
+    }
+
+    Some(Completion {
+        start,
+        candidates,
+        selected: 0,
+    })
+}
+
+fn complete_from(words: &[&str], prefix: &str) -> Vec<Candidate> {
+    words
+        .iter()
+        .filter(|word| word.starts_with(prefix))
+        .map(|word| candidate(word.to_string()))
+        .collect()
+}
+
+fn candidate(replacement: String) -> Candidate {
+    Candidate {
+        display: replacement.clone(),
+        replacement,
+    }
+}
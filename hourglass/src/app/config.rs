@@ -0,0 +1,466 @@
+use chrono::format::{Item, StrftimeItems};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+use toml::Value;
+
+use super::storage::{Backend, Scope, StorageFormat};
+use super::ui::theme::{Theme, ThemeSpec};
+use crate::util::config_dir;
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const REPO_CONFIG_FILE_NAME: &str = ".hourglass.toml";
+
+// the tabs hourglass knows how to render
+pub const KNOWN_TABS: [&str; 3] = ["tasks", "issues", "archive"];
+
+// a cloned repo could otherwise send the CalDAV password to a server of its choosing
+const USER_ONLY_TABLES: [&str; 1] = ["caldav"];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// strftime-like format used for every date shown in the UI
+    pub time_format: String,
+    /// name of the file tasks are saved to
+    pub storage_file: String,
+    /// name of the file archived tasks are moved to
+    pub archive_file: String,
+    /// json keeps tasks in storage_file and archive_file, sqlite in database_file
+    pub backend: Backend,
+    pub database_file: String,
+    /// what the tasks tab shows at startup: the tasks of this repo, the global ones or all
+    pub scope: Scope,
+    /// compact (one line) or lines (one task per line, for task files kept in git)
+    pub storage_format: StorageFormat,
+    /// completed tasks are archived on startup once they have been done for that long, 0 to never
+    pub archive_after_days: u64,
+    /// how often the UI is redrawn, in milliseconds
+    pub tick_rate_ms: u64,
+    /// how often issues are fetched again, in seconds
+    pub issues_refresh_secs: u64,
+    /// a built-in theme (dark, light, high-contrast) or one from `themes`
+    pub theme: String,
+    pub tabs: Vec<String>,
+    pub columns: Columns,
+    pub themes: BTreeMap<String, ThemeSpec>,
+    pub caldav: CalDav,
+}
+
+/// Widths of the table columns, in percent of the table width
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Columns {
+    pub id: u16,
+    pub description: u16,
+    pub age: u16,
+}
+
+/// The CalDAV task collection `hourglass sync` talks to, the password comes from
+/// `HOURGLASS_CALDAV_PASSWORD` so it never ends up in a file
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalDav {
+    /// e.g. http://localhost:5232/user/tasks/, syncing is off while empty
+    pub url: String,
+    pub username: String,
+    /// name of the file remembering which task is which remote resource, and their ETags
+    pub state_file: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            time_format: String::from("%b %d, %Y %I:%M %p"),
+            storage_file: String::from("tasks.hourglass"),
+            // not a `.hourglass` file, those are all read as task lists
+            archive_file: String::from("tasks.hourglass-archive"),
+            backend: Backend::default(),
+            database_file: String::from("tasks.hourglass-db"),
+            scope: Scope::default(),
+            storage_format: StorageFormat::default(),
+            archive_after_days: 14,
+            tick_rate_ms: 250,
+            issues_refresh_secs: 30,
+            theme: String::from("dark"),
+            tabs: KNOWN_TABS.iter().map(|tab| tab.to_string()).collect(),
+            columns: Columns::default(),
+            themes: BTreeMap::new(),
+            caldav: CalDav {
+                state_file: String::from("tasks.hourglass-sync"),
+                ..CalDav::default()
+            },
+        }
+    }
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            id: 15,
+            description: 75,
+            age: 10,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub source: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid configuration in {}: {}",
+            self.source, self.message
+        )
+    }
+}
+
+/// The effective configuration and the layers it was built from
+pub struct LoadedConfig {
+    pub config: Config,
+    pub sources: Vec<String>,
+}
+
+impl Config {
+    pub fn user_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("hourglass").join(CONFIG_FILE_NAME))
+    }
+
+    /// Defaults, then the user config, then the closest `.hourglass.toml`, then `HOURGLASS_*` env vars.
+    /// `caldav` is only read from the user config and the env vars
+    pub fn load() -> Result<LoadedConfig, ConfigError> {
+        let mut merged = Table::new();
+        let mut sources = vec![String::from("defaults")];
+
+        let repo_path = env::current_dir()
+            .ok()
+            .and_then(|dir| find_repo_config(&dir));
+
+        let layers = Config::user_path()
+            .map(|path| (path, false))
+            .into_iter()
+            .chain(repo_path.map(|path| (path, true)));
+
+        for (path, is_repo) in layers {
+            if path.exists() {
+                let table = read_table(&path)?;
+
+                if is_repo {
+                    check_repo_table(&path, &table)?;
+                }
+
+                merge(&mut merged, table);
+                sources.push(path.display().to_string());
+            }
+        }
+
+        let mut config: Config =
+            Value::Table(merged)
+                .try_into()
+                .map_err(|err: toml::de::Error| ConfigError {
+                    source: sources.join(", "),
+                    message: err.to_string(),
+                })?;
+
+        if config.apply_env()? {
+            sources.push(String::from("environment"));
+        }
+
+        config.validate()?;
+
+        Ok(LoadedConfig { config, sources })
+    }
+
+    // returns whether any variable was set
+    fn apply_env(&mut self) -> Result<bool, ConfigError> {
+        let mut applied = false;
+
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        let invalid = |name: &str, value: &str| ConfigError {
+            source: String::from("environment"),
+            message: format!("{}={} is not valid", name, value),
+        };
+
+        if let Some(value) = var("HOURGLASS_TIME_FORMAT") {
+            self.time_format = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_STORAGE_FILE") {
+            self.storage_file = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_ARCHIVE_FILE") {
+            self.archive_file = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_BACKEND") {
+            self.backend =
+                Backend::parse(&value).ok_or_else(|| invalid("HOURGLASS_BACKEND", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_DATABASE_FILE") {
+            self.database_file = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_SCOPE") {
+            self.scope = Scope::parse(&value).ok_or_else(|| invalid("HOURGLASS_SCOPE", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_STORAGE_FORMAT") {
+            self.storage_format = StorageFormat::parse(&value)
+                .ok_or_else(|| invalid("HOURGLASS_STORAGE_FORMAT", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_ARCHIVE_AFTER_DAYS") {
+            self.archive_after_days = value
+                .parse()
+                .map_err(|_| invalid("HOURGLASS_ARCHIVE_AFTER_DAYS", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_TICK_RATE_MS") {
+            self.tick_rate_ms = value
+                .parse()
+                .map_err(|_| invalid("HOURGLASS_TICK_RATE_MS", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_ISSUES_REFRESH_SECS") {
+            self.issues_refresh_secs = value
+                .parse()
+                .map_err(|_| invalid("HOURGLASS_ISSUES_REFRESH_SECS", &value))?;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_THEME") {
+            self.theme = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_CALDAV_URL") {
+            self.caldav.url = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_CALDAV_USERNAME") {
+            self.caldav.username = value;
+            applied = true;
+        }
+
+        if let Some(value) = var("HOURGLASS_TABS") {
+            self.tabs = value.split(',').map(|tab| tab.trim().to_string()).collect();
+            applied = true;
+        }
+
+        // e.g. HOURGLASS_COLUMNS=10,80,10
+        if let Some(value) = var("HOURGLASS_COLUMNS") {
+            let widths: Vec<u16> = value
+                .split(',')
+                .map(|width| width.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid("HOURGLASS_COLUMNS", &value))?;
+
+            match widths.as_slice() {
+                [id, description, age] => {
+                    self.columns = Columns {
+                        id: *id,
+                        description: *description,
+                        age: *age,
+                    }
+                }
+                _ => return Err(invalid("HOURGLASS_COLUMNS", &value)),
+            }
+            applied = true;
+        }
+
+        Ok(applied)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let error = |message: String| ConfigError {
+            source: String::from("effective configuration"),
+            message,
+        };
+
+        if self.storage_file.trim().is_empty() {
+            return Err(error(String::from("storage_file cannot be empty")));
+        }
+
+        if self.archive_file.trim().is_empty() || self.archive_file == self.storage_file {
+            return Err(error(String::from(
+                "archive_file cannot be empty or the same as storage_file",
+            )));
+        }
+
+        if self.database_file.trim().is_empty()
+            || [&self.storage_file, &self.archive_file].contains(&&self.database_file)
+        {
+            return Err(error(String::from(
+                "database_file cannot be empty or the same as storage_file or archive_file",
+            )));
+        }
+
+        if self.tick_rate_ms == 0 {
+            return Err(error(String::from("tick_rate_ms must be greater than 0")));
+        }
+
+        if self.issues_refresh_secs == 0 {
+            return Err(error(String::from(
+                "issues_refresh_secs must be greater than 0",
+            )));
+        }
+
+        // chrono panics on an invalid specifier only once a date gets formatted
+        if StrftimeItems::new(&self.time_format).any(|item| item == Item::Error) {
+            return Err(error(format!(
+                "time_format `{}` is not a valid strftime format",
+                self.time_format
+            )));
+        }
+
+        if self.tabs.is_empty() {
+            return Err(error(String::from("tabs cannot be empty")));
+        }
+
+        if let Some(tab) = self
+            .tabs
+            .iter()
+            .find(|tab| !KNOWN_TABS.contains(&tab.as_str()))
+        {
+            return Err(error(format!(
+                "unknown tab `{}`, expected one of {}",
+                tab,
+                KNOWN_TABS.join(", ")
+            )));
+        }
+
+        self.resolve_theme().map_err(error)?;
+
+        if !self.caldav.url.is_empty() {
+            url::Url::parse(&self.caldav.url)
+                .map_err(|err| error(format!("caldav.url `{}`: {}", self.caldav.url, err)))?;
+
+            let state_file = &self.caldav.state_file;
+
+            if state_file.trim().is_empty()
+                || [&self.storage_file, &self.archive_file, &self.database_file]
+                    .contains(&state_file)
+            {
+                return Err(error(String::from(
+                    "caldav.state_file cannot be empty or the same as storage_file, archive_file or database_file",
+                )));
+            }
+        }
+
+        let total = self.columns.id + self.columns.description + self.columns.age;
+
+        if total > 100 {
+            return Err(error(format!(
+                "column widths add up to {}%, they cannot exceed 100%",
+                total
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve_theme(&self) -> Result<Theme, String> {
+        Theme::resolve(&self.theme, &self.themes)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, ConfigError> {
+    let error = |message: String| ConfigError {
+        source: path.display().to_string(),
+        message,
+    };
+
+    let content = fs::read_to_string(path).map_err(|err| error(err.to_string()))?;
+
+    toml::from_str(&content).map_err(|err: toml::de::Error| error(err.to_string()))
+}
+
+fn check_repo_table(path: &Path, table: &Table) -> Result<(), ConfigError> {
+    match USER_ONLY_TABLES
+        .iter()
+        .find(|key| table.contains_key(**key))
+    {
+        Some(key) => Err(ConfigError {
+            source: path.display().to_string(),
+            message: format!(
+                "[{}] can only be set in the user {} or with HOURGLASS_* environment variables",
+                key, CONFIG_FILE_NAME
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+// later layers win, tables are merged key by key instead of being replaced
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// looks for `.hourglass.toml` in `dir` and its parents, stopping at the root of the git repo
+fn find_repo_config(dir: &Path) -> Option<PathBuf> {
+    for dir in dir.ancestors() {
+        let path = dir.join(REPO_CONFIG_FILE_NAME);
+
+        if path.exists() {
+            return Some(path);
+        }
+
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_config_cannot_set_caldav() {
+        let path = Path::new(REPO_CONFIG_FILE_NAME);
+        let table: Table = toml::from_str("[caldav]\nurl = \"https://example.com/\"").unwrap();
+
+        let err = check_repo_table(path, &table).unwrap_err();
+
+        assert_eq!(err.source, REPO_CONFIG_FILE_NAME);
+        assert!(err
+            .message
+            .starts_with("[caldav] can only be set in the user config.toml"));
+        assert!(check_repo_table(path, &toml::from_str("theme = \"light\"").unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::app::task::Task;
+
+/// Every column that can be exported, in the order `--columns` lists them
+pub const COLUMNS: [&str; 12] = [
+    "id",
+    "uuid",
+    "description",
+    "completed",
+    "tags",
+    "project",
+    "priority",
+    "due",
+    "notes",
+    "created_at",
+    "modified_at",
+    "completed_at",
+];
+
+// the columns exported when none are chosen
+const DEFAULT_COLUMNS: [&str; 9] = [
+    "id",
+    "description",
+    "completed",
+    "tags",
+    "project",
+    "priority",
+    "due",
+    "created_at",
+    "modified_at",
+];
+
+/// Writes `columns` in that order, the default ones when there are none
+pub fn export(tasks: &[&Task], columns: &[String]) -> String {
+    let columns: Vec<&str> = if columns.is_empty() {
+        DEFAULT_COLUMNS.to_vec()
+    } else {
+        columns.iter().map(String::as_str).collect()
+    };
+
+    let mut lines = vec![columns.join(",")];
+
+    for task in tasks {
+        let values: Vec<String> = columns
+            .iter()
+            .map(|column| escape(&value(task, column)))
+            .collect();
+
+        lines.push(values.join(","));
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+
+    content
+}
+
+fn value(task: &Task, column: &str) -> String {
+    match column {
+        "id" => task.id.to_string(),
+        "uuid" => task.uuid.to_string(),
+        "description" => task.description.clone(),
+        "completed" => task.completed.to_string(),
+        "tags" => task.tags.join(" "),
+        "project" => task.project.clone().unwrap_or_default(),
+        "priority" => task
+            .priority
+            .map(|priority| priority.as_str().to_string())
+            .unwrap_or_default(),
+        "due" => task.due.map(|due| due.to_rfc3339()).unwrap_or_default(),
+        "notes" => task.notes.clone(),
+        "created_at" => task.created_at.to_rfc3339(),
+        "modified_at" => task.modified_at.to_rfc3339(),
+        "completed_at" => task
+            .completed_at
+            .map(|completed_at| completed_at.to_rfc3339())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+// RFC 4180: fields with separators, quotes or line breaks are quoted, quotes are doubled
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,604 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use uuid::Uuid;
+
+use crate::app::task::{Priority, Task};
+use crate::util::date_format::COMPACT_FORMAT;
+
+// extensions hourglass understands, every other one is written as an `X-HOURGLASS-` property
+const RRULE: &str = "rrule";
+// the UID of a task that came from another app, when it isn't a uuid
+const UID: &str = "uid";
+const PROJECT: &str = "project";
+const EXTENSION_PREFIX: &str = "X-HOURGLASS-";
+// content lines longer than this are folded, in octets
+const MAX_LINE_LENGTH: usize = 75;
+
+// https://datatracker.ietf.org/doc/html/rfc5545#section-3.6.2
+pub fn export(tasks: &[&Task]) -> String {
+    let now = Utc::now();
+
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        String::from("PRODID:-//hourglass//hourglass//EN"),
+    ];
+
+    for task in tasks {
+        lines.push(String::from("BEGIN:VTODO"));
+        lines.extend(export_task(task, now));
+        lines.push(String::from("END:VTODO"));
+    }
+
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines
+        .iter()
+        .map(|line| format!("{}\r\n", fold(line)))
+        .collect()
+}
+
+fn export_task(task: &Task, now: DateTime<Utc>) -> Vec<String> {
+    let uid = match task.extensions.get(UID) {
+        Some(uid) => uid.clone(),
+        None => task.uuid.to_string(),
+    };
+
+    let mut lines = vec![
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", format_date(now)),
+        format!("CREATED:{}", format_date(task.created_at)),
+        format!("LAST-MODIFIED:{}", format_date(task.modified_at)),
+        format!("SUMMARY:{}", escape(&task.description)),
+    ];
+
+    if !task.notes.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&task.notes)));
+    }
+
+    if task.completed {
+        let completed_at = task.completed_at.unwrap_or(task.modified_at);
+
+        lines.push(String::from("STATUS:COMPLETED"));
+        lines.push(format!("COMPLETED:{}", format_date(completed_at)));
+    } else {
+        lines.push(String::from("STATUS:NEEDS-ACTION"));
+    }
+
+    if let Some(due) = task.due {
+        lines.push(format!("DUE:{}", format_date(due)));
+    }
+
+    if let Some(priority) = task.priority {
+        lines.push(format!("PRIORITY:{}", export_priority(priority)));
+    }
+
+    if !task.tags.is_empty() {
+        let tags: Vec<String> = task.tags.iter().map(|tag| escape(tag)).collect();
+
+        lines.push(format!("CATEGORIES:{}", tags.join(",")));
+    }
+
+    if let Some(rrule) = task.extensions.get(RRULE) {
+        lines.push(format!("RRULE:{}", rrule));
+    }
+
+    if let Some(project) = &task.project {
+        lines.push(format!(
+            "{}{}:{}",
+            EXTENSION_PREFIX,
+            PROJECT.to_uppercase(),
+            escape(project)
+        ));
+    }
+
+    for (key, value) in task.extensions.iter() {
+        // property names are letters, digits and dashes
+        let is_name = key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        if key != UID && key != RRULE && is_name {
+            lines.push(format!(
+                "{}{}:{}",
+                EXTENSION_PREFIX,
+                key.to_uppercase(),
+                escape(value)
+            ));
+        }
+    }
+
+    lines
+}
+
+/// The VTODO components of a calendar, cancelled ones are skipped
+pub fn import(content: &str) -> Result<Vec<Task>, String> {
+    let mut tasks = vec![];
+    // the properties of the VTODO being read, and the line it started on
+    let mut current: Option<(usize, Vec<Property>)> = None;
+    // components nested in a VTODO, like VALARM, are skipped
+    let mut nested = 0;
+
+    for (number, line) in unfold(content) {
+        let property =
+            parse_property(&line).ok_or_else(|| format!("line {}: invalid line", number))?;
+
+        match (
+            property.name.as_str(),
+            property.value.as_str(),
+            &mut current,
+        ) {
+            ("BEGIN", "VTODO", None) => current = Some((number, vec![])),
+            ("BEGIN", _, Some(_)) => nested += 1,
+            ("END", "VTODO", Some(_)) if nested == 0 => {
+                if let Some((number, properties)) = current.take() {
+                    if let Some(task) = import_task(properties)
+                        .map_err(|err| format!("VTODO on line {}: {}", number, err))?
+                    {
+                        tasks.push(task);
+                    }
+                }
+            }
+            ("END", _, Some(_)) if nested > 0 => nested -= 1,
+            (_, _, Some((_, properties))) if nested == 0 => properties.push(property),
+            _ => {}
+        }
+    }
+
+    if let Some((number, _)) = current {
+        return Err(format!("VTODO on line {} is never closed", number));
+    }
+
+    Ok(tasks)
+}
+
+fn import_task(properties: Vec<Property>) -> Result<Option<Task>, String> {
+    let now = Utc::now();
+    let mut task = Task::new(0, Default::default());
+    let mut created_at = None;
+    let mut modified_at = None;
+
+    for property in properties {
+        let value = property.value.as_str();
+        let invalid = || format!("invalid {} `{}`", property.name, value);
+
+        match property.name.as_str() {
+            "UID" => match Uuid::parse_str(value) {
+                Ok(uuid) => task.uuid = uuid,
+                // the same UID always gives the same uuid, so importing again updates the task
+                Err(_) => {
+                    task.uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, value.as_bytes());
+                    task.extensions.insert(UID.to_string(), value.to_string());
+                }
+            },
+            "SUMMARY" => task.description = unescape(value),
+            "DESCRIPTION" => task.notes = unescape(value),
+            "STATUS" => match value {
+                "COMPLETED" => task.completed = true,
+                "CANCELLED" => return Ok(None),
+                _ => {}
+            },
+            "COMPLETED" => task.completed_at = Some(parse_date(&property).ok_or_else(invalid)?),
+            "DUE" => task.due = Some(parse_date(&property).ok_or_else(invalid)?),
+            "CREATED" => created_at = Some(parse_date(&property).ok_or_else(invalid)?),
+            "LAST-MODIFIED" => modified_at = Some(parse_date(&property).ok_or_else(invalid)?),
+            "PRIORITY" => {
+                task.priority = import_priority(value.parse().map_err(|_| invalid())?);
+            }
+            "CATEGORIES" => {
+                for tag in split_list(value) {
+                    if !tag.is_empty() && !task.tags.contains(&tag) {
+                        task.tags.push(tag);
+                    }
+                }
+            }
+            "RRULE" => {
+                task.extensions.insert(RRULE.to_string(), value.to_string());
+            }
+            name => {
+                if let Some(key) = name.strip_prefix(EXTENSION_PREFIX) {
+                    let key = key.to_lowercase();
+
+                    if key == PROJECT {
+                        task.project = Some(unescape(value)).filter(|p| !p.is_empty());
+                    } else {
+                        task.extensions.insert(key, unescape(value));
+                    }
+                }
+            }
+        }
+    }
+
+    // a COMPLETED date without the status still means the task is done
+    task.completed = task.completed || task.completed_at.is_some();
+    task.created_at = created_at.unwrap_or(now);
+    task.modified_at = modified_at.unwrap_or(task.created_at);
+
+    Ok(Some(task))
+}
+
+// 1 to 4 is high, 5 medium and 6 to 9 low, 0 is no priority
+fn export_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+fn import_priority(priority: u8) -> Option<Priority> {
+    match priority {
+        1..=4 => Some(Priority::High),
+        5 => Some(Priority::Medium),
+        6..=9 => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+struct Property {
+    name: String,
+    // `VALUE=DATE`, `TZID=...`
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// `NAME;PARAM=value;PARAM="quoted:value":value`
+fn parse_property(line: &str) -> Option<Property> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.trim().to_uppercase();
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let params = parts
+        .filter_map(|part| part.split_once('='))
+        .map(|(param, value)| (param.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+// joins the folded lines back, with the number of the line each one starts on
+fn unfold(content: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+
+    for (i, line) in content.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => last.push_str(rest),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push((i + 1, line.to_string())),
+        }
+    }
+
+    lines
+}
+
+// splits lines longer than 75 octets without cutting a character in half
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded.push_str("\r\n ");
+            // the leading space counts
+            length = 1;
+        }
+
+        folded.push(c);
+        length += c.len_utf8();
+    }
+
+    folded
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+// CATEGORIES is a comma separated list where a value can contain an escaped comma
+fn split_list(value: &str) -> Vec<String> {
+    let mut values = vec![];
+    let mut current = String::new();
+    let mut escaped = false;
+
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => values.push(unescape(&std::mem::take(&mut current))),
+            c => {
+                escaped = c == '\\' && !escaped;
+                current.push(c);
+            }
+        }
+    }
+
+    values.push(unescape(&current));
+
+    values
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format(COMPACT_FORMAT).to_string()
+}
+
+// UTC times end with `Z`, dates are the end of that day like the due dates typed in the
+// command bar, times without `Z` are read as local time whatever their TZID
+fn parse_date(property: &Property) -> Option<DateTime<Utc>> {
+    let value = property.value.trim();
+
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+
+        return local_to_utc(date.and_time(NaiveTime::from_hms_opt(23, 59, 59)?));
+    }
+
+    match value.strip_suffix('Z') {
+        Some(_) => NaiveDateTime::parse_from_str(value, COMPACT_FORMAT)
+            .ok()
+            .map(|date| date.and_utc()),
+        None => local_to_utc(NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?),
+    }
+}
+
+fn local_to_utc(date: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&date)
+        .earliest()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(task: &Task) -> Task {
+        let mut tasks = import(&export(&[task])).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        tasks.remove(0)
+    }
+
+    fn date(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, day, hour, 30, 0).unwrap()
+    }
+
+    fn task(description: &str) -> Task {
+        let mut task = Task::new(1, Default::default());
+        task.description = description.to_string();
+        task.created_at = date(1, 9);
+        task.modified_at = date(2, 10);
+
+        task
+    }
+
+    fn calendar(lines: &[&str]) -> String {
+        let mut content = vec!["BEGIN:VCALENDAR", "BEGIN:VTODO"];
+        content.extend(lines);
+        content.extend(["END:VTODO", "END:VCALENDAR"]);
+
+        content.join("\r\n")
+    }
+
+    #[test]
+    fn keeps_fields() {
+        let mut task = task("Call mom");
+        task.project = Some(String::from("family"));
+        task.tags = vec![String::from("phone"), String::from("home")];
+        task.priority = Some(Priority::Medium);
+        task.due = Some(date(5, 18));
+        task.notes = String::from("ask about\nthe sale");
+        task.extensions
+            .insert(String::from(RRULE), String::from("FREQ=WEEKLY"));
+        task.extensions
+            .insert(String::from("estimate"), String::from("3"));
+
+        let imported = round_trip(&task);
+
+        assert_eq!(imported.uuid, task.uuid);
+        assert_eq!(imported.description, task.description);
+        assert_eq!(imported.project, task.project);
+        assert_eq!(imported.tags, task.tags);
+        assert_eq!(imported.priority, task.priority);
+        assert_eq!(imported.due, task.due);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(imported.extensions, task.extensions);
+        assert_eq!(imported.created_at, task.created_at);
+        assert_eq!(imported.modified_at, task.modified_at);
+        assert!(!imported.completed);
+    }
+
+    #[test]
+    fn keeps_completion() {
+        let mut task = task("Done");
+        task.completed = true;
+        task.completed_at = Some(date(4, 12));
+
+        let exported = export(&[&task]);
+        let imported = round_trip(&task);
+
+        assert!(exported.contains("STATUS:COMPLETED\r\nCOMPLETED:20230604T123000Z\r\n"));
+        assert!(imported.completed);
+        assert_eq!(imported.completed_at, task.completed_at);
+    }
+
+    #[test]
+    fn escapes_text() {
+        let mut task = task(r"Buy milk, eggs; bread \ butter");
+        task.notes = String::from("first line\nsecond, line");
+        task.tags = vec![String::from("a,b"), String::from("c;d")];
+        task.project = Some(String::from("home, garden"));
+
+        let exported = export(&[&task]);
+        let imported = round_trip(&task);
+
+        assert!(exported.contains(r"SUMMARY:Buy milk\, eggs\; bread \\ butter"));
+        assert!(exported.contains(r"DESCRIPTION:first line\nsecond\, line"));
+        assert!(exported.contains(r"CATEGORIES:a\,b,c\;d"));
+        assert_eq!(imported.description, task.description);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(imported.tags, task.tags);
+        assert_eq!(imported.project, task.project);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let description = "é".repeat(60) + &"a".repeat(60);
+        let task = task(&description);
+
+        let exported = export(&[&task]);
+
+        assert!(exported
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(exported.contains("\r\n "));
+        assert_eq!(round_trip(&task).description, description);
+    }
+
+    #[test]
+    fn maps_priorities() {
+        let priorities = [
+            (0, None),
+            (1, Some(Priority::High)),
+            (4, Some(Priority::High)),
+            (5, Some(Priority::Medium)),
+            (6, Some(Priority::Low)),
+            (9, Some(Priority::Low)),
+        ];
+
+        for (number, priority) in priorities {
+            let line = format!("PRIORITY:{}", number);
+            let imported = import(&calendar(&["SUMMARY:Task", &line])).unwrap();
+
+            assert_eq!(imported[0].priority, priority, "{}", line);
+        }
+
+        for priority in [Priority::High, Priority::Medium, Priority::Low] {
+            let mut task = task("Task");
+            task.priority = Some(priority);
+
+            assert_eq!(round_trip(&task).priority, Some(priority));
+        }
+    }
+
+    #[test]
+    fn reads_dates() {
+        let local = |date: NaiveDateTime| local_to_utc(date).unwrap();
+        let day = NaiveDate::from_ymd_opt(2023, 6, 5).unwrap();
+
+        let content = calendar(&[
+            "SUMMARY:Task",
+            "DUE;VALUE=DATE:20230605",
+            "CREATED;TZID=Europe/Paris:20230605T083000",
+            "LAST-MODIFIED:20230605T083000Z",
+        ]);
+        let imported = import(&content).unwrap().remove(0);
+
+        assert_eq!(
+            imported.due,
+            Some(local(day.and_hms_opt(23, 59, 59).unwrap()))
+        );
+        assert_eq!(
+            imported.created_at,
+            local(day.and_hms_opt(8, 30, 0).unwrap())
+        );
+        assert_eq!(imported.modified_at, date(5, 8));
+    }
+
+    #[test]
+    fn keeps_the_uid_of_other_apps() {
+        let content = calendar(&["UID:1234@example.com", "SUMMARY:Task"]);
+
+        let imported = import(&content).unwrap().remove(0);
+        let again = import(&content).unwrap().remove(0);
+
+        assert_eq!(imported.uuid, again.uuid);
+        assert_eq!(imported.extensions[UID], "1234@example.com");
+        assert!(export(&[&imported]).contains("\r\nUID:1234@example.com\r\n"));
+        assert_eq!(round_trip(&imported).uuid, imported.uuid);
+    }
+
+    #[test]
+    fn skips_cancelled_tasks_and_nested_components() {
+        let content = [
+            "BEGIN:VCALENDAR",
+            "BEGIN:VTODO",
+            "SUMMARY:Task",
+            "BEGIN:VALARM",
+            "SUMMARY:Alarm",
+            "END:VALARM",
+            "COMPLETED:20230604T123000Z",
+            "END:VTODO",
+            "BEGIN:VTODO",
+            "SUMMARY:Cancelled",
+            "STATUS:CANCELLED",
+            "END:VTODO",
+            "END:VCALENDAR",
+        ]
+        .join("\n");
+
+        let imported = import(&content).unwrap();
+
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].description, "Task");
+        assert!(imported[0].completed);
+    }
+
+    #[test]
+    fn reports_invalid_calendars() {
+        assert_eq!(
+            import(&calendar(&["SUMMARY:Task", "DUE:tomorrow"])).unwrap_err(),
+            "VTODO on line 2: invalid DUE `tomorrow`"
+        );
+        assert_eq!(
+            import("BEGIN:VTODO\nSUMMARY:Task").unwrap_err(),
+            "VTODO on line 1 is never closed"
+        );
+        assert_eq!(
+            import("BEGIN:VTODO\nnot a property").unwrap_err(),
+            "line 2: invalid line"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use super::todotxt::{escape_word, unescape};
+use crate::app::formats::GroupBy;
+use crate::app::task::{parse_input, Task};
+
+// notes are indented under their task so they stay part of the list item
+const NOTES_INDENT: &str = "  ";
+
+// a GitHub-flavored markdown checklist, one section per project or tag:
+//
+// ## house
+//
+// - [ ] Buy milk +home project:house priority:H due:2023-06-01
+//   semi-skimmed
+// - [x] Fix the door project:house
+pub fn export(tasks: &[&Task], group_by: GroupBy) -> String {
+    // tasks without a project or a tag go last, under `None`
+    let mut groups: BTreeMap<Option<&str>, Vec<&Task>> = BTreeMap::new();
+
+    for &task in tasks {
+        let group = match group_by {
+            GroupBy::Project => task.project.as_deref(),
+            GroupBy::Tag => task.tags.first().map(String::as_str),
+            GroupBy::None => None,
+        };
+
+        groups.entry(group).or_default().push(task);
+    }
+
+    let none = groups.remove(&None);
+    let has_headings = !groups.is_empty();
+
+    let mut sections: Vec<(String, Vec<&Task>)> = groups
+        .into_iter()
+        .map(|(group, tasks)| (group.unwrap_or_default().to_string(), tasks))
+        .collect();
+
+    if let Some(tasks) = none {
+        let title = match group_by {
+            GroupBy::Project => "No project",
+            GroupBy::Tag => "No tag",
+            GroupBy::None => "",
+        };
+
+        sections.push((title.to_string(), tasks));
+    }
+
+    let mut content = String::new();
+
+    for (title, tasks) in sections {
+        if has_headings {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+
+            content.push_str(&format!("## {}\n\n", title));
+        }
+
+        for task in tasks {
+            let checkbox = if task.completed { "x" } else { " " };
+            let words: Vec<String> = task
+                .description
+                .split_whitespace()
+                .map(escape_description_word)
+                .collect();
+            let input = Task {
+                description: words.join(" "),
+                ..(*task).clone()
+            }
+            .to_input();
+
+            content.push_str(&format!("- [{}] {}\n", checkbox, input));
+
+            for line in task.notes.lines() {
+                match line.is_empty() {
+                    true => content.push('\n'),
+                    false => content.push_str(&format!("{}{}\n", NOTES_INDENT, line)),
+                }
+            }
+        }
+    }
+
+    content
+}
+
+/// Every `- [ ]` and `- [x]` line is a task, read like the command bar input with the
+/// escapes of `export` decoded.
+/// Indented lines under a task are its notes, everything else is left out
+pub fn import(content: &str) -> Result<Vec<Task>, String> {
+    let mut tasks: Vec<Task> = vec![];
+    let mut notes: Vec<&str> = vec![];
+    // blank lines are only part of the notes when more indented lines follow
+    let mut blank_lines = 0;
+    let mut in_task = false;
+
+    for line in content.lines() {
+        if let Some((completed, input)) = parse_checklist_item(line) {
+            finish_notes(tasks.last_mut(), &mut notes);
+
+            let mut task = Task::new(0, parse_input(input));
+            task.description = unescape(&task.description);
+
+            if completed {
+                task.set_completed(true);
+            }
+
+            tasks.push(task);
+            blank_lines = 0;
+            in_task = true;
+        } else if line.trim().is_empty() {
+            blank_lines += 1;
+        } else if let (true, Some(note)) = (in_task, line.strip_prefix(NOTES_INDENT)) {
+            if !notes.is_empty() {
+                notes.extend((0..blank_lines).map(|_| ""));
+            }
+
+            notes.push(note);
+            blank_lines = 0;
+        } else {
+            in_task = false;
+        }
+    }
+
+    finish_notes(tasks.last_mut(), &mut notes);
+
+    Ok(tasks)
+}
+
+// escaped like todo.txt words, plus the `project:a:b` words todo.txt doesn't take for extensions
+fn escape_description_word(word: &str) -> String {
+    let escaped = escape_word(word);
+
+    match parse_input(&escaped).description == escaped {
+        true => escaped,
+        false => escaped.replacen(':', "%3A", 1),
+    }
+}
+
+fn finish_notes(task: Option<&mut Task>, notes: &mut Vec<&str>) {
+    if let Some(task) = task {
+        if !notes.is_empty() {
+            task.notes = notes.join("\n");
+        }
+    }
+
+    notes.clear();
+}
+
+// `- [ ] text`, `* [x] text` or `+ [X] text`, at any indentation
+fn parse_checklist_item(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?
+        .trim_start();
+
+    let (completed, input) = if let Some(input) = rest.strip_prefix("[ ]") {
+        (false, input)
+    } else if let Some(input) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, input)
+    } else {
+        return None;
+    };
+
+    let input = input.trim();
+
+    (!input.is_empty()).then_some((completed, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::task::Priority;
+
+    fn round_trip(task: &Task) -> Task {
+        let mut tasks = import(&export(&[task], GroupBy::Project)).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        tasks.remove(0)
+    }
+
+    #[test]
+    fn keeps_fields() {
+        let mut task = Task::new(1, parse_input("Call mom +phone project:family priority:M"));
+        task.due = parse_input("due:2023-06-01").due;
+        task.notes = String::from("ask about\n\nthe sale");
+        task.set_completed(true);
+
+        let imported = round_trip(&task);
+
+        assert_eq!(imported.description, task.description);
+        assert_eq!(imported.tags, task.tags);
+        assert_eq!(imported.project, task.project);
+        assert_eq!(imported.priority, Some(Priority::Medium));
+        assert_eq!(imported.due, task.due);
+        assert_eq!(imported.notes, task.notes);
+        assert!(imported.completed);
+    }
+
+    #[test]
+    fn escapes_description_words() {
+        let description = "+1 for project:a:b due:tomorrow priority:h at 10:30, 50% %41 +";
+        let mut task = Task::new(1, Default::default());
+        task.description = description.to_string();
+
+        let exported = export(&[&task], GroupBy::Project);
+        let imported = round_trip(&task);
+
+        assert!(exported.starts_with("- [ ] %2B1 for project%3Aa:b due%3Atomorrow priority%3Ah"));
+        assert_eq!(imported.description, description);
+        assert!(imported.tags.is_empty());
+        assert_eq!(imported.project, None);
+        assert_eq!(imported.priority, None);
+        assert_eq!(imported.due, None);
+    }
+
+    #[test]
+    fn groups_tasks() {
+        let home = Task::new(1, parse_input("Fix the door project:house"));
+        let other = Task::new(2, parse_input("Read"));
+
+        assert_eq!(
+            export(&[&other, &home], GroupBy::Project),
+            "## house\n\n- [ ] Fix the door project:house\n\n## No project\n\n- [ ] Read\n"
+        );
+        assert_eq!(
+            export(&[&other, &home], GroupBy::None),
+            "- [ ] Read\n- [ ] Fix the door project:house\n"
+        );
+    }
+
+    #[test]
+    fn reads_checklist_items_only() {
+        let content = "# Todo\n\nSome text\n* [X] Done\n  + [ ] Nested\n    note\n- [ ]\n- item\n";
+
+        let imported = import(content).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        assert!(imported[0].completed);
+        assert_eq!(imported[1].description, "Nested");
+        assert_eq!(imported[1].notes, "  note");
+    }
+}
@@ -0,0 +1,96 @@
+use crate::app::task::Task;
+
+mod csv;
+mod ical;
+mod markdown;
+mod taskwarrior;
+mod todotxt;
+
+pub use csv::COLUMNS as CSV_COLUMNS;
+
+/// File formats tasks can be exported to and imported from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    TodoTxt,
+    Taskwarrior,
+    ICalendar,
+    Markdown,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 5] = ["csv", "todotxt", "taskwarrior", "ical", "markdown"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "todotxt" | "todo.txt" => Some(Format::TodoTxt),
+            "taskwarrior" | "tw" => Some(Format::Taskwarrior),
+            "ical" | "ics" | "icalendar" => Some(Format::ICalendar),
+            "markdown" | "md" => Some(Format::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::TodoTxt => "todotxt",
+            Format::Taskwarrior => "taskwarrior",
+            Format::ICalendar => "ical",
+            Format::Markdown => "markdown",
+        }
+    }
+}
+
+/// Settings of the formats that have some, the others ignore them
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    // csv columns, in order, the default ones when empty
+    pub columns: Vec<String>,
+    // sections of the markdown checklist
+    pub group_by: GroupBy,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GroupBy {
+    #[default]
+    Project,
+    // the first tag of each task
+    Tag,
+    None,
+}
+
+impl GroupBy {
+    pub const NAMES: [&'static str; 3] = ["project", "tag", "none"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "project" => Some(GroupBy::Project),
+            "tag" => Some(GroupBy::Tag),
+            "none" => Some(GroupBy::None),
+            _ => None,
+        }
+    }
+}
+
+pub fn export(tasks: &[&Task], format: Format, options: &ExportOptions) -> String {
+    match format {
+        Format::Csv => csv::export(tasks, &options.columns),
+        Format::TodoTxt => todotxt::export(tasks),
+        Format::Taskwarrior => taskwarrior::export(tasks),
+        Format::ICalendar => ical::export(tasks),
+        Format::Markdown => markdown::export(tasks, options.group_by),
+    }
+}
+
+/// The tasks found in `content`, ids and positions are left for the caller to assign
+pub fn import(content: &str, format: Format) -> Result<Vec<Task>, String> {
+    match format {
+        Format::TodoTxt => todotxt::import(content),
+        Format::Taskwarrior => taskwarrior::import(content),
+        Format::ICalendar => ical::import(content),
+        Format::Markdown => markdown::import(content),
+        other => Err(format!("cannot import from {}", other.name())),
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::app::task::{Annotation, Priority, Task};
+use crate::util::date_format;
+
+// hourglass has no field for these statuses, they are kept so exporting gives them back
+const STATUS: &str = "status";
+// not a Taskwarrior attribute, exported as a user defined one
+const NOTES: &str = "notes";
+// computed by Taskwarrior, importing them back would be meaningless
+const IGNORED: [&str; 2] = ["id", "urgency"];
+
+// https://taskwarrior.org/docs/design/task/
+#[derive(Serialize, Deserialize)]
+struct TaskwarriorTask {
+    #[serde(default)]
+    uuid: Uuid,
+    description: String,
+    status: String,
+    #[serde(
+        default,
+        with = "date_format::compact::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    entry: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        with = "date_format::compact::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    modified: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        with = "date_format::compact::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    end: Option<DateTime<Utc>>,
+    #[serde(
+        default,
+        with = "date_format::compact::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    due: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<TaskwarriorAnnotation>,
+    // user defined attributes and everything else
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TaskwarriorAnnotation {
+    #[serde(with = "date_format::compact")]
+    entry: DateTime<Utc>,
+    description: String,
+}
+
+/// A JSON array like `task export` writes, `task import` reads it back
+pub fn export(tasks: &[&Task]) -> String {
+    let tasks: Vec<TaskwarriorTask> = tasks.iter().map(|task| export_task(task)).collect();
+
+    let mut content = serde_json::to_string_pretty(&tasks).unwrap();
+    content.push('\n');
+
+    content
+}
+
+fn export_task(task: &Task) -> TaskwarriorTask {
+    let status = if task.completed {
+        String::from("completed")
+    } else {
+        task.extensions
+            .get(STATUS)
+            .cloned()
+            .unwrap_or_else(|| String::from("pending"))
+    };
+
+    let mut other: BTreeMap<String, Value> = task
+        .extensions
+        .iter()
+        .filter(|(key, _)| *key != STATUS)
+        .map(|(key, value)| (key.clone(), to_json(value)))
+        .collect();
+
+    if !task.notes.is_empty() {
+        other.insert(NOTES.to_string(), Value::String(task.notes.clone()));
+    }
+
+    TaskwarriorTask {
+        uuid: task.uuid,
+        description: task.description.clone(),
+        status,
+        entry: Some(task.created_at),
+        modified: Some(task.modified_at),
+        end: task
+            .completed
+            .then(|| task.completed_at.unwrap_or(task.modified_at)),
+        due: task.due,
+        tags: task.tags.clone(),
+        project: task.project.clone(),
+        priority: task.priority,
+        annotations: task
+            .annotations
+            .iter()
+            .map(|annotation| TaskwarriorAnnotation {
+                entry: annotation.entry,
+                description: annotation.description.clone(),
+            })
+            .collect(),
+        other,
+    }
+}
+
+/// A JSON array, or one task per line like older versions of `task export`.
+/// Deleted tasks are skipped
+pub fn import(content: &str) -> Result<Vec<Task>, String> {
+    let tasks: Vec<TaskwarriorTask> = match serde_json::from_str(content) {
+        Ok(tasks) => tasks,
+        Err(err) if content.trim_start().starts_with('[') => return Err(err.to_string()),
+        Err(_) => content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim().trim_end_matches(',')))
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|err| format!("line {}: {}", i + 1, err))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+
+    Ok(tasks
+        .into_iter()
+        .filter(|task| task.status != "deleted")
+        .map(import_task)
+        .collect())
+}
+
+fn import_task(imported: TaskwarriorTask) -> Task {
+    let now = Utc::now();
+    let mut task = Task::new(0, Default::default());
+
+    if !imported.uuid.is_nil() {
+        task.uuid = imported.uuid;
+    }
+
+    task.description = imported.description;
+    task.completed = imported.status == "completed";
+    task.completed_at = imported.end.filter(|_| task.completed);
+    task.created_at = imported.entry.unwrap_or(now);
+    task.modified_at = imported.modified.unwrap_or(task.created_at);
+    task.due = imported.due;
+    task.tags = imported.tags;
+    task.project = imported.project;
+    task.priority = imported.priority;
+    task.annotations = imported
+        .annotations
+        .into_iter()
+        .map(|annotation| Annotation {
+            entry: annotation.entry,
+            description: annotation.description,
+        })
+        .collect();
+
+    if !matches!(imported.status.as_str(), "pending" | "completed") {
+        task.extensions.insert(STATUS.to_string(), imported.status);
+    }
+
+    for (key, value) in imported.other {
+        match (key.as_str(), value) {
+            (key, _) if IGNORED.contains(&key) => {}
+            (NOTES, Value::String(notes)) => task.notes = notes,
+            (_, Value::String(value)) => {
+                task.extensions.insert(key, value);
+            }
+            (_, value) => {
+                task.extensions.insert(key, value.to_string());
+            }
+        }
+    }
+
+    task
+}
+
+// extensions are strings, numeric attributes were kept as their JSON text
+fn to_json(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(json) if !json.is_string() => json,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn round_trip(task: &Task) -> Task {
+        let mut tasks = import(&export(&[task])).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        tasks.remove(0)
+    }
+
+    fn date(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 6, day, hour, 30, 0).unwrap()
+    }
+
+    fn task(description: &str) -> Task {
+        let mut task = Task::new(1, Default::default());
+        task.description = description.to_string();
+        task.created_at = date(1, 9);
+        task.modified_at = date(2, 10);
+
+        task
+    }
+
+    #[test]
+    fn keeps_fields() {
+        let mut task = task("Call mom");
+        task.project = Some(String::from("family"));
+        task.tags = vec![String::from("phone"), String::from("home")];
+        task.priority = Some(Priority::Medium);
+        task.due = Some(date(5, 18));
+        task.notes = String::from("ask about\nthe sale");
+        task.annotations = vec![Annotation {
+            entry: date(3, 8),
+            description: String::from("she called back"),
+        }];
+
+        let imported = round_trip(&task);
+
+        assert_eq!(imported.uuid, task.uuid);
+        assert_eq!(imported.description, task.description);
+        assert_eq!(imported.project, task.project);
+        assert_eq!(imported.tags, task.tags);
+        assert_eq!(imported.priority, task.priority);
+        assert_eq!(imported.due, task.due);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(imported.annotations, task.annotations);
+        assert_eq!(imported.created_at, task.created_at);
+        assert_eq!(imported.modified_at, task.modified_at);
+        assert!(imported.extensions.is_empty());
+        assert!(!imported.completed);
+    }
+
+    #[test]
+    fn writes_taskwarrior_attributes() {
+        let mut task = task("Call mom");
+        task.priority = Some(Priority::High);
+        task.due = Some(date(5, 18));
+        task.notes = String::from("ask");
+
+        let exported: Value = serde_json::from_str(&export(&[&task])).unwrap();
+
+        assert_eq!(exported[0]["status"], "pending");
+        assert_eq!(exported[0]["priority"], "H");
+        assert_eq!(exported[0]["entry"], "20230601T093000Z");
+        assert_eq!(exported[0]["due"], "20230605T183000Z");
+        assert_eq!(exported[0]["notes"], "ask");
+        assert!(exported[0].get("end").is_none());
+    }
+
+    #[test]
+    fn keeps_completion() {
+        let mut task = task("Done");
+        task.completed = true;
+        task.completed_at = Some(date(4, 12));
+
+        let exported = export(&[&task]);
+        let imported = round_trip(&task);
+
+        assert!(exported.contains("\"status\": \"completed\""));
+        assert!(exported.contains("\"end\": \"20230604T123000Z\""));
+        assert!(imported.completed);
+        assert_eq!(imported.completed_at, task.completed_at);
+    }
+
+    #[test]
+    fn keeps_other_statuses_and_udas() {
+        let content = r#"[{"uuid":"0b0c1f1e-6c2b-4c5f-9a6e-2f1d3c4b5a69","description":"Later",
+            "status":"waiting","wait":"20230610T000000Z","estimate":3,"id":4,"urgency":1.8}]"#;
+
+        let imported = import(content).unwrap().remove(0);
+
+        assert!(!imported.completed);
+        assert_eq!(imported.extensions[STATUS], "waiting");
+        assert_eq!(imported.extensions["wait"], "20230610T000000Z");
+        assert_eq!(imported.extensions["estimate"], "3");
+        assert!(!imported.extensions.contains_key("id"));
+        assert!(!imported.extensions.contains_key("urgency"));
+
+        let exported: Value = serde_json::from_str(&export(&[&imported])).unwrap();
+
+        assert_eq!(exported[0]["status"], "waiting");
+        assert_eq!(exported[0]["wait"], "20230610T000000Z");
+        assert_eq!(exported[0]["estimate"], 3);
+        assert_eq!(round_trip(&imported).extensions, imported.extensions);
+    }
+
+    #[test]
+    fn reads_one_task_per_line_and_skips_deleted_ones() {
+        let content = r#"{"description":"First","status":"pending","priority":"L"},
+            {"description":"Gone","status":"deleted"}
+            {"description":"Second","status":"completed","end":"20230604T123000Z"}"#;
+
+        let imported = import(content).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].description, "First");
+        assert_eq!(imported[0].priority, Some(Priority::Low));
+        assert!(!imported[0].uuid.is_nil());
+        assert_eq!(imported[1].description, "Second");
+        assert_eq!(imported[1].completed_at, Some(date(4, 12)));
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let content = "{\"description\":\"First\",\"status\":\"pending\"}\n{\"description\":";
+
+        assert!(import(content).unwrap_err().starts_with("line 2: "));
+        assert!(import("[{\"description\":1}]").is_err());
+    }
+}
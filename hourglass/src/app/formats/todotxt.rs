@@ -0,0 +1,436 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use uuid::Uuid;
+
+use crate::app::task::{Priority, Task};
+
+// extensions hourglass understands, every other one is kept in `Task::extensions`
+const DUE: &str = "due";
+const NOTES: &str = "notes";
+// todo.txt drops the priority of completed tasks, the usual convention keeps it as `pri:A`
+const PRIORITY: &str = "pri";
+// importing an exported file again updates the tasks instead of adding them twice
+const UUID: &str = "uuid";
+
+// https://github.com/todotxt/todo.txt
+//
+// x 2023-06-02 2023-05-28 +family Call mom @phone due:2023-06-01 t:2023-05-30
+// ^ ^          ^          ^       ^        ^      ^              ^
+// | completion creation   project |        tag    due            kept as is
+// completed                       description
+pub fn export(tasks: &[&Task]) -> String {
+    let mut content = String::new();
+
+    for task in tasks {
+        content.push_str(&export_task(task));
+        content.push('\n');
+    }
+
+    content
+}
+
+fn export_task(task: &Task) -> String {
+    let mut words = vec![];
+    let priority = task
+        .priority
+        .map(|priority| priority_letter(task, priority));
+
+    if task.completed {
+        let completed_at = task.completed_at.unwrap_or(task.modified_at);
+
+        words.push(String::from("x"));
+        words.push(format_date(completed_at));
+    } else if let Some(letter) = priority {
+        words.push(format!("({})", letter));
+    }
+
+    words.push(format_date(task.created_at));
+
+    // before the description, where a `+word` would otherwise be taken for the project
+    if let Some(project) = &task.project {
+        words.push(format!("+{}", project));
+    }
+
+    words.extend(task.description.split_whitespace().map(escape_word));
+
+    words.extend(task.tags.iter().map(|tag| format!("@{}", tag)));
+
+    if let Some(due) = task.due {
+        words.push(format!("{}:{}", DUE, format_date(due)));
+    }
+
+    if let (true, Some(letter)) = (task.completed, priority) {
+        words.push(format!("{}:{}", PRIORITY, letter));
+    }
+
+    if !task.notes.is_empty() {
+        words.push(format!("{}:{}", NOTES, escape(&task.notes)));
+    }
+
+    words.push(format!("{}:{}", UUID, task.uuid));
+
+    for (key, value) in task.extensions.iter() {
+        if key != PRIORITY && key != UUID {
+            words.push(format!("{}:{}", key, value));
+        }
+    }
+
+    words.retain(|word| !word.is_empty());
+    words.join(" ")
+}
+
+/// Every non-empty line is a task, ids and positions are left for the caller to assign
+pub fn import(content: &str) -> Result<Vec<Task>, String> {
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(import_task)
+        .collect())
+}
+
+fn import_task(line: &str) -> Task {
+    let now = Utc::now();
+    let mut words = line.split_whitespace().peekable();
+
+    let completed = words.next_if_eq(&"x").is_some();
+    let mut letter = None;
+    let mut completed_at = None;
+
+    if completed {
+        completed_at = words.next_if(|word| parse_date(word).is_some());
+    } else {
+        letter = words
+            .next_if(|word| parse_priority(word).is_some())
+            .and_then(parse_priority);
+    }
+
+    let created_at = words.next_if(|word| parse_date(word).is_some());
+
+    let mut task = Task::new(0, Default::default());
+    let mut description = vec![];
+
+    task.completed = completed;
+    task.completed_at = completed_at.and_then(parse_date);
+    task.created_at = created_at.and_then(parse_date).unwrap_or(now);
+
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|project| !project.is_empty()) {
+            // hourglass has a single project, the others become tags
+            if task.project.is_none() {
+                task.project = Some(project.to_string());
+            } else if task.project.as_deref() != Some(project)
+                && !task.tags.iter().any(|t| t == project)
+            {
+                task.tags.push(project.to_string());
+            }
+        } else if let Some(tag) = word.strip_prefix('@').filter(|tag| !tag.is_empty()) {
+            if !task.tags.iter().any(|t| t == tag) {
+                task.tags.push(tag.to_string());
+            }
+        } else if let Some((key, value)) = parse_extension(word) {
+            match key {
+                DUE if parse_date(value).is_some() => task.due = parse_due(value),
+                NOTES => task.notes = unescape(value),
+                UUID => match Uuid::parse_str(value) {
+                    Ok(uuid) => task.uuid = uuid,
+                    Err(_) => {
+                        task.extensions.insert(key.to_string(), value.to_string());
+                    }
+                },
+                PRIORITY if letter.is_none() && parse_letter(value).is_some() => {
+                    letter = parse_letter(value);
+                }
+                _ => {
+                    task.extensions.insert(key.to_string(), value.to_string());
+                }
+            }
+        } else {
+            description.push(unescape(word));
+        }
+    }
+
+    task.description = description.join(" ");
+
+    if let Some(letter) = letter {
+        task.priority = Some(match letter {
+            'A' => Priority::High,
+            'B' => Priority::Medium,
+            _ => Priority::Low,
+        });
+
+        // hourglass only has three priorities, keep the letter so exporting gives it back
+        if letter > 'C' {
+            task.extensions
+                .insert(PRIORITY.to_string(), letter.to_string());
+        }
+    }
+
+    task.modified_at = task.completed_at.unwrap_or(task.created_at);
+
+    task
+}
+
+// `(A)` to `(Z)`
+fn parse_priority(word: &str) -> Option<char> {
+    parse_letter(word.strip_prefix('(')?.strip_suffix(')')?)
+}
+
+fn parse_letter(word: &str) -> Option<char> {
+    let mut chars = word.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) if letter.is_ascii_uppercase() => Some(letter),
+        _ => None,
+    }
+}
+
+// the letter of a low priority that came from a todo.txt priority below `C`
+fn priority_letter(task: &Task, priority: Priority) -> char {
+    match priority {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => task
+            .extensions
+            .get(PRIORITY)
+            .and_then(|letter| parse_letter(letter))
+            .filter(|letter| *letter > 'C')
+            .unwrap_or('C'),
+    }
+}
+
+// `key:value` where neither side is empty or has a colon, URLs like `https://...` aren't extensions
+fn parse_extension(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+
+    if key.is_empty() || value.is_empty() || value.contains(':') || value.starts_with("//") {
+        return None;
+    }
+
+    Some((key, value))
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    let date: DateTime<Local> = DateTime::from(date);
+
+    date.format("%Y-%m-%d").to_string()
+}
+
+// todo.txt dates have no time, they are read as the start of the day in local time
+fn parse_date(word: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()?;
+
+    local_to_utc(date, NaiveTime::MIN)
+}
+
+// like the due dates typed in the command bar, the end of the day
+fn parse_due(word: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()?;
+
+    local_to_utc(date, NaiveTime::from_hms_opt(23, 59, 59)?)
+}
+
+fn local_to_utc(date: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+// a description word that would be read back as a project, tag or extension gets its
+// first `+`, `@` or `:` percent-encoded, like a `%` that already looks like an escape
+pub(super) fn escape_word(word: &str) -> String {
+    let mut escaped = String::new();
+    let mut rest = word;
+
+    while let Some(c) = rest.chars().next() {
+        let tail = &rest[c.len_utf8()..];
+        let is_code = tail.len() >= 2 && tail.as_bytes()[..2].iter().all(u8::is_ascii_hexdigit);
+
+        match c {
+            '%' if is_code => escaped.push_str("%25"),
+            c => escaped.push(c),
+        }
+
+        rest = tail;
+    }
+
+    if (escaped.starts_with('+') || escaped.starts_with('@')) && escaped.len() > 1 {
+        let c = escaped.remove(0);
+        escaped.insert_str(0, &format!("%{:02X}", c as u8));
+    }
+
+    if parse_extension(&escaped).is_some() {
+        escaped = escaped.replacen(':', "%3A", 1);
+    }
+
+    escaped
+}
+
+// a task is a single line and an extension a single word, so notes are percent-encoded
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in text.chars() {
+        match c {
+            '%' | ':' | '/' | ' ' | '\t' | '\n' | '\r' => {
+                escaped.push_str(&format!("%{:02X}", c as u8))
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+pub(super) fn unescape(text: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        let code = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (byte, code) {
+            (b'%', Some(code)) => {
+                bytes.push(code);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(task: &Task) -> Task {
+        let mut tasks = import(&export(&[task])).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        tasks.remove(0)
+    }
+
+    fn task(description: &str) -> Task {
+        let mut task = Task::new(1, Default::default());
+        task.description = description.to_string();
+        task.created_at = parse_date("2023-05-28").unwrap();
+
+        task
+    }
+
+    #[test]
+    fn keeps_fields() {
+        let mut task = task("Call mom");
+        task.project = Some(String::from("family"));
+        task.tags = vec![String::from("phone"), String::from("home")];
+        task.priority = Some(Priority::Medium);
+        task.due = parse_due("2023-06-01");
+        task.notes = String::from("ask about: the 50% off\nsale");
+        task.extensions
+            .insert(String::from("t"), String::from("2023-05-30"));
+
+        let imported = round_trip(&task);
+
+        assert_eq!(imported.description, task.description);
+        assert_eq!(imported.project, task.project);
+        assert_eq!(imported.tags, task.tags);
+        assert_eq!(imported.priority, task.priority);
+        assert_eq!(imported.due, task.due);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(imported.extensions, task.extensions);
+        assert_eq!(imported.created_at, task.created_at);
+        assert!(!imported.completed);
+    }
+
+    #[test]
+    fn keeps_completion_and_priority() {
+        let mut task = task("Done");
+        task.completed = true;
+        task.completed_at = parse_date("2023-06-02");
+        task.priority = Some(Priority::High);
+
+        let imported = round_trip(&task);
+
+        assert!(imported.completed);
+        assert_eq!(imported.completed_at, task.completed_at);
+        assert_eq!(imported.priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn keeps_low_priority_letters() {
+        let imported = import("(E) 2023-05-28 Someday").unwrap().remove(0);
+
+        assert_eq!(imported.priority, Some(Priority::Low));
+        assert!(export(&[&imported]).starts_with("(E) 2023-05-28 Someday uuid:"));
+    }
+
+    #[test]
+    fn escapes_description_words() {
+        let description = "+1 for @home at 10:30 key:value +x:y, https://example.com 50% %41";
+        let task = task(description);
+
+        let imported = round_trip(&task);
+
+        assert_eq!(imported.description, description);
+        assert_eq!(imported.project, None);
+        assert!(imported.tags.is_empty());
+        assert!(imported.extensions.is_empty());
+    }
+
+    #[test]
+    fn keeps_description_words_readable() {
+        let task = task("Pay 50% of the + fee, see https://example.com");
+
+        assert_eq!(
+            export_task(&task),
+            format!(
+                "2023-05-28 Pay 50% of the + fee, see https://example.com uuid:{}",
+                task.uuid
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_the_uuid() {
+        let task = task("Same task");
+
+        assert_eq!(round_trip(&task).uuid, task.uuid);
+
+        // not a uuid, kept like any other extension
+        let imported = import("2023-05-28 Other uuid:nope").unwrap().remove(0);
+        assert_eq!(
+            imported.extensions.get(UUID).map(String::as_str),
+            Some("nope")
+        );
+    }
+
+    #[test]
+    fn keeps_extra_projects_as_tags() {
+        let imported = import("2023-05-28 +home Fix the sink +diy @weekend +home")
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(imported.project.as_deref(), Some("home"));
+        assert_eq!(imported.description, "Fix the sink");
+        assert_eq!(imported.tags, ["diy", "weekend"]);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(
+            parse_extension("due:2023-06-01"),
+            Some(("due", "2023-06-01"))
+        );
+        assert_eq!(parse_extension("https://example.com"), None);
+        assert_eq!(parse_extension("10:30:00"), None);
+        assert_eq!(parse_extension(":value"), None);
+        assert_eq!(parse_extension("key:"), None);
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::util::data_dir;
+
+const HISTORY_FILE_NAME: &str = "history";
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// Everything entered in the command bar, persisted across sessions in the data dir
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+    // position while browsing with Up/Down, `None` when not browsing
+    index: Option<usize>,
+    // what was typed before browsing started, restored when going past the newest entry
+    draft: String,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        let path = data_dir().map(|dir| dir.join("hourglass").join(HISTORY_FILE_NAME));
+
+        let entries = match &path {
+            Some(path) => fs::read_to_string(path)
+                .map(|content| content.lines().map(String::from).collect())
+                .unwrap_or_default(),
+            None => vec![],
+        };
+
+        History {
+            entries,
+            path,
+            ..History::default()
+        }
+    }
+
+    pub fn push(&mut self, entry: &str) {
+        self.reset();
+
+        let entry = entry.trim();
+
+        // skip empty lines and consecutive duplicates, like most shells do
+        if entry.is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+
+        self.entries.push(entry.to_string());
+
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            self.entries.remove(0);
+            self.save();
+        } else {
+            self.append(entry);
+        }
+    }
+
+    /// Moves to the previous (older) entry, `current` is what is in the command bar right now
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let index = match self.index {
+            Some(0) => return None,
+            Some(i) => i - 1,
+            None => {
+                if self.entries.is_empty() {
+                    return None;
+                }
+
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+        };
+
+        self.index = Some(index);
+
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Moves to the next (newer) entry, going past the newest one gives back the draft
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.index?;
+
+        if index + 1 >= self.entries.len() {
+            self.index = None;
+
+            return Some(&self.draft);
+        }
+
+        self.index = Some(index + 1);
+
+        self.entries.get(index + 1).map(String::as_str)
+    }
+
+    pub fn reset(&mut self) {
+        self.index = None;
+        self.draft.clear();
+    }
+
+    fn append(&self, entry: &str) {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).ok();
+            }
+
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                writeln!(file, "{}", entry).ok();
+            }
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            let mut content = self.entries.join("\n");
+            content.push('\n');
+
+            fs::write(path, content).ok();
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rustyline::line_buffer::{ChangeListener, LineBuffer};
+use rustyline::{At, Word};
+
+/// Readline-style editing of the command input, returns whether the key was used
+pub fn edit_line<C: ChangeListener>(
+    line: &mut LineBuffer,
+    key_event: KeyEvent,
+    cl: &mut C,
+) -> bool {
+    let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+
+    match key_event.code {
+        KeyCode::Char(c) if ctrl => match c {
+            'a' => line.move_home(),
+            'e' => line.move_end(),
+            'b' => line.move_backward(1),
+            'f' => line.move_forward(1),
+            'h' => line.backspace(1, cl),
+            'd' => line.delete(1, cl).is_some(),
+            'w' => line.delete_prev_word(Word::Big, 1, cl),
+            'u' => line.discard_line(cl),
+            'k' => line.kill_line(cl),
+            't' => line.transpose_chars(cl),
+            _ => return false,
+        },
+        KeyCode::Char(c) if alt => match c {
+            'b' => line.move_to_prev_word(Word::Emacs, 1),
+            'f' => line.move_to_next_word(At::AfterEnd, Word::Emacs, 1),
+            'd' => line.delete_word(At::AfterEnd, Word::Emacs, 1, cl),
+            _ => return false,
+        },
+        KeyCode::Char(c) => line.insert(c, 1, cl).is_some(),
+        KeyCode::Left if ctrl || alt => line.move_to_prev_word(Word::Emacs, 1),
+        KeyCode::Right if ctrl || alt => line.move_to_next_word(At::AfterEnd, Word::Emacs, 1),
+        KeyCode::Left => line.move_backward(1),
+        KeyCode::Right => line.move_forward(1),
+        KeyCode::Home => line.move_home(),
+        KeyCode::End => line.move_end(),
+        KeyCode::Backspace if ctrl || alt => line.delete_prev_word(Word::Emacs, 1, cl),
+        KeyCode::Backspace => line.backspace(1, cl),
+        KeyCode::Delete => line.delete(1, cl).is_some(),
+        _ => return false,
+    };
+
+    true
+}
+
+/// Inserts pasted text at the cursor, the command input is a single line so newlines become spaces
+pub fn paste<C: ChangeListener>(line: &mut LineBuffer, text: &str, cl: &mut C) {
+    let text = text.replace(['\r', '\n'], " ");
+
+    line.yank(&text, 1, cl);
+}
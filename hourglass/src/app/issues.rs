@@ -9,7 +9,7 @@ use std::env;
 use std::process::Command;
 use url::Url;
 
-use crate::app::cache::ISSUES_CACHE;
+use crate::app::cache::{COMMENTS_CACHE, ISSUES_CACHE};
 
 // struct GitRepo {
 //     name: String,
@@ -35,6 +35,20 @@ pub struct RepoIssue {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub user: GitUser,
+    #[serde(default)]
+    pub labels: Vec<IssueLabel>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IssueLabel {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct IssueComment {
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub user: GitUser,
 }
 
 pub fn get_issues() -> Option<Vec<RepoIssue>> {
@@ -42,11 +56,57 @@ pub fn get_issues() -> Option<Vec<RepoIssue>> {
         return Some(issues.to_vec());
     }
 
-    let output = Command::new("git")
-        .arg("remote")
-        .arg("-v")
-        .output()
-        .expect("Failed to get git remotes");
+    let (owner, name) = get_repo()?;
+
+    let request_url = format!(
+        "https://api.github.com/repos/{owner}/{repo}/issues",
+        owner = owner,
+        repo = name
+    );
+
+    let issues: Vec<RepoIssue> = github_get(&request_url, &owner)?.json().ok()?;
+
+    ISSUES_CACHE
+        .lock()
+        .unwrap()
+        .insert("issues", issues.clone());
+
+    Some(issues)
+}
+
+pub fn cached_issue_comments(number: u32) -> Option<Vec<IssueComment>> {
+    COMMENTS_CACHE.lock().unwrap().get(&number).cloned()
+}
+
+/// Blocks until GitHub answers when the comments aren't cached, `None` when the request
+/// fails or the answer isn't a list of comments, e.g. a 401 or a rate limit error
+pub fn get_issue_comments(number: u32) -> Option<Vec<IssueComment>> {
+    if let Some(comments) = cached_issue_comments(number) {
+        return Some(comments);
+    }
+
+    let (owner, name) = get_repo()?;
+
+    let request_url = format!(
+        "https://api.github.com/repos/{owner}/{repo}/issues/{number}/comments",
+        owner = owner,
+        repo = name,
+        number = number
+    );
+
+    let comments: Vec<IssueComment> = github_get(&request_url, &owner)?.json().ok()?;
+
+    COMMENTS_CACHE
+        .lock()
+        .unwrap()
+        .insert(number, comments.clone());
+
+    Some(comments)
+}
+
+// returns the (owner, name) of the repo the fetch remote points to
+fn get_repo() -> Option<(String, String)> {
+    let output = Command::new("git").arg("remote").arg("-v").output().ok()?;
 
     let data = String::from_utf8_lossy(&output.stdout).to_string();
 
@@ -60,62 +120,39 @@ pub fn get_issues() -> Option<Vec<RepoIssue>> {
 
     let fetch_remote = remotes[0];
 
-    let push_remote = remotes[1];
-
     let re = Regex::new(r"(.+)\s+(.+)\s+\((push|fetch)\)").unwrap();
 
-    let capture = re.captures_iter(fetch_remote).next();
-
-    if let Some(capture) = capture {
-        let url = &capture[2];
-        let repo_type = &capture[3];
-
-        let url = Url::parse(url).expect("Unable to parse Git url");
-
-        let (owner, name) = parse_git_url(url.path());
+    let capture = re.captures_iter(fetch_remote).next()?;
 
-        let request_url = format!(
-            "https://api.github.com/repos/{owner}/{repo}/issues",
-            owner = owner,
-            repo = name
-        );
+    let url = Url::parse(&capture[2]).ok()?;
 
-        let client = reqwest::blocking::Client::new();
+    let (owner, name) = parse_git_url(url.path())?;
 
-        let access_token =
-            env::var("GITHUB_ACCESS_TOKEN").expect("unable to get github access token");
-
-        let res = client
-            .get(request_url)
-            .bearer_auth(access_token)
-            .header("X-GitHub-Api-Version", "2022-11-28")
-            .header(ACCEPT, "application/vnd.github+json")
-            .header(USER_AGENT, owner)
-            .send()
-            .expect("Unable to get issues");
+    Some((owner.to_string(), name.to_string()))
+}
 
-        let issues: Vec<RepoIssue> = res.json().expect("Unable to parse json resposne");
+// `None` without a GITHUB_ACCESS_TOKEN or when GitHub can't be reached
+fn github_get(request_url: &str, owner: &str) -> Option<reqwest::blocking::Response> {
+    let client = reqwest::blocking::Client::new();
 
-        ISSUES_CACHE
-            .lock()
-            .unwrap()
-            .insert("issues", issues.clone());
+    let access_token = env::var("GITHUB_ACCESS_TOKEN").ok()?;
 
-        return Some(issues);
-    } else {
-        return None;
-    }
+    client
+        .get(request_url)
+        .bearer_auth(access_token)
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header(ACCEPT, "application/vnd.github+json")
+        .header(USER_AGENT, owner)
+        .send()
+        .ok()
 }
 
-fn parse_git_url(url: &str) -> (&str, &str) {
+fn parse_git_url(url: &str) -> Option<(&str, &str)> {
     let repo_info: Vec<&str> = url.split("/").collect();
-    let owner = repo_info.get(1).expect("Unable to get owner of repo");
-    let name = repo_info
-        .get(2)
-        .expect("Unable to get name of repo")
-        .trim_end_matches(".git");
+    let owner = repo_info.get(1)?;
+    let name = repo_info.get(2)?.trim_end_matches(".git");
 
-    (owner, name)
+    Some((owner, name))
 }
 
 fn get_lines(input: &str) -> Vec<&str> {
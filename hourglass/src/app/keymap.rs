@@ -0,0 +1,733 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const KEYMAP_FILE_NAME: &str = "keys.toml";
+
+/// Everything a key can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyAction {
+    Quit,
+    Next,
+    Previous,
+    First,
+    Last,
+    PageDown,
+    PageUp,
+    NextTab,
+    PreviousTab,
+    ToggleFocus,
+    GrowTable,
+    ShrinkTable,
+    Command,
+    Add,
+    Update,
+    Toggle,
+    Delete,
+    EditNotes,
+    Help,
+    Mark,
+    MarkRange,
+    MarkAll,
+    ClearMarks,
+    Modify,
+    Undo,
+    MoveUp,
+    MoveDown,
+    Restore,
+    Purge,
+    CycleScope,
+    MoveToList,
+    NewList,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 32] = [
+        KeyAction::Quit,
+        KeyAction::Next,
+        KeyAction::Previous,
+        KeyAction::First,
+        KeyAction::Last,
+        KeyAction::PageDown,
+        KeyAction::PageUp,
+        KeyAction::NextTab,
+        KeyAction::PreviousTab,
+        KeyAction::ToggleFocus,
+        KeyAction::GrowTable,
+        KeyAction::ShrinkTable,
+        KeyAction::Command,
+        KeyAction::Add,
+        KeyAction::Update,
+        KeyAction::Toggle,
+        KeyAction::Delete,
+        KeyAction::EditNotes,
+        KeyAction::Help,
+        KeyAction::Mark,
+        KeyAction::MarkRange,
+        KeyAction::MarkAll,
+        KeyAction::ClearMarks,
+        KeyAction::Modify,
+        KeyAction::Undo,
+        KeyAction::MoveUp,
+        KeyAction::MoveDown,
+        KeyAction::Restore,
+        KeyAction::Purge,
+        KeyAction::CycleScope,
+        KeyAction::MoveToList,
+        KeyAction::NewList,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::Next => "next",
+            KeyAction::Previous => "previous",
+            KeyAction::First => "first",
+            KeyAction::Last => "last",
+            KeyAction::PageDown => "page_down",
+            KeyAction::PageUp => "page_up",
+            KeyAction::NextTab => "next_tab",
+            KeyAction::PreviousTab => "previous_tab",
+            KeyAction::ToggleFocus => "toggle_focus",
+            KeyAction::GrowTable => "grow_table",
+            KeyAction::ShrinkTable => "shrink_table",
+            KeyAction::Command => "command",
+            KeyAction::Add => "add",
+            KeyAction::Update => "update",
+            KeyAction::Toggle => "toggle",
+            KeyAction::Delete => "delete",
+            KeyAction::EditNotes => "edit_notes",
+            KeyAction::Help => "help",
+            KeyAction::Mark => "mark",
+            KeyAction::MarkRange => "mark_range",
+            KeyAction::MarkAll => "mark_all",
+            KeyAction::ClearMarks => "clear_marks",
+            KeyAction::Modify => "modify",
+            KeyAction::Undo => "undo",
+            KeyAction::MoveUp => "move_up",
+            KeyAction::MoveDown => "move_down",
+            KeyAction::Restore => "restore",
+            KeyAction::Purge => "purge",
+            KeyAction::CycleScope => "cycle_scope",
+            KeyAction::MoveToList => "move_to_list",
+            KeyAction::NewList => "new_list",
+        }
+    }
+
+    /// What the action does, shown in the help popup
+    pub fn description(&self) -> &'static str {
+        match self {
+            KeyAction::Quit => "Quit hourglass",
+            KeyAction::Next => "Select the next row, or scroll the details down",
+            KeyAction::Previous => "Select the previous row, or scroll the details up",
+            KeyAction::First => "Select the first row, or scroll to the top of the details",
+            KeyAction::Last => "Select the last row, or scroll to the bottom of the details",
+            KeyAction::PageDown => "Scroll the details down a page",
+            KeyAction::PageUp => "Scroll the details up a page",
+            KeyAction::NextTab => "Go to the next tab",
+            KeyAction::PreviousTab => "Go to the previous tab",
+            KeyAction::ToggleFocus => "Move the focus between the table and the details",
+            KeyAction::GrowTable => "Make the table taller",
+            KeyAction::ShrinkTable => "Make the table shorter",
+            KeyAction::Command => "Open the command bar",
+            KeyAction::Add => "Add a task",
+            KeyAction::Update => "Update the selected task, or modify the marked ones",
+            KeyAction::Toggle => "Mark the selected or marked tasks as done or not done",
+            KeyAction::Delete => "Delete the selected or marked tasks",
+            KeyAction::EditNotes => "Edit the notes of the selected task in $EDITOR",
+            KeyAction::Help => "Show the key bindings",
+            KeyAction::Mark => "Mark or unmark the selected task",
+            KeyAction::MarkRange => {
+                "Mark every task between the last marked one and the selected one"
+            }
+            KeyAction::MarkAll => "Mark every task shown, or unmark them if they all are",
+            KeyAction::ClearMarks => "Unmark every task",
+            KeyAction::Modify => {
+                "Change the tags, project, priority or due date of the marked tasks"
+            }
+            KeyAction::Undo => "Undo the last change to the tasks",
+            KeyAction::MoveUp => "Move the selected task up, when the list isn't sorted",
+            KeyAction::MoveDown => "Move the selected task down, when the list isn't sorted",
+            KeyAction::Restore => "Move the selected archived task back to the tasks, as not done",
+            KeyAction::Purge => "Delete the selected archived task for good",
+            KeyAction::CycleScope => "Show the tasks of this repo, the global ones or both",
+            KeyAction::MoveToList => "Move the selected or marked tasks to another list",
+            KeyAction::NewList => "Create a task list, or go to one",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        KeyAction::ALL
+            .iter()
+            .find(|action| action.name() == name)
+            .copied()
+    }
+}
+
+/// The views that have their own set of bindings, `Global` ones apply everywhere
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyContext {
+    Global,
+    Tasks,
+    Issues,
+    Archive,
+}
+
+impl KeyContext {
+    pub const ALL: [KeyContext; 4] = [
+        KeyContext::Global,
+        KeyContext::Tasks,
+        KeyContext::Issues,
+        KeyContext::Archive,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyContext::Global => "global",
+            KeyContext::Tasks => "tasks",
+            KeyContext::Issues => "issues",
+            KeyContext::Archive => "archive",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        KeyContext::ALL
+            .iter()
+            .find(|context| context.name() == name)
+            .copied()
+    }
+}
+
+const DEFAULT_BINDINGS: [(KeyContext, KeyAction, &[&str]); 32] = [
+    (KeyContext::Global, KeyAction::Quit, &["q"]),
+    (KeyContext::Global, KeyAction::Next, &["j", "<Down>"]),
+    (KeyContext::Global, KeyAction::Previous, &["k", "<Up>"]),
+    (KeyContext::Global, KeyAction::First, &["g", "<Home>"]),
+    (KeyContext::Global, KeyAction::Last, &["G", "<End>"]),
+    (KeyContext::Global, KeyAction::PageDown, &["<PageDown>"]),
+    (KeyContext::Global, KeyAction::PageUp, &["<PageUp>"]),
+    (KeyContext::Global, KeyAction::NextTab, &["]"]),
+    (KeyContext::Global, KeyAction::PreviousTab, &["["]),
+    (KeyContext::Global, KeyAction::ToggleFocus, &["<Tab>"]),
+    (KeyContext::Global, KeyAction::GrowTable, &["+"]),
+    (KeyContext::Global, KeyAction::ShrinkTable, &["-"]),
+    (KeyContext::Global, KeyAction::Command, &[":"]),
+    (KeyContext::Global, KeyAction::Help, &["?"]),
+    (KeyContext::Tasks, KeyAction::Add, &["a"]),
+    (KeyContext::Tasks, KeyAction::Update, &["u"]),
+    (KeyContext::Tasks, KeyAction::Toggle, &["d"]),
+    (KeyContext::Tasks, KeyAction::Delete, &["x"]),
+    (KeyContext::Tasks, KeyAction::EditNotes, &["n"]),
+    (KeyContext::Tasks, KeyAction::Mark, &["<Space>"]),
+    (KeyContext::Tasks, KeyAction::MarkRange, &["V"]),
+    (KeyContext::Tasks, KeyAction::MarkAll, &["*"]),
+    (KeyContext::Tasks, KeyAction::ClearMarks, &["<Esc>"]),
+    (KeyContext::Tasks, KeyAction::Modify, &["m"]),
+    (KeyContext::Tasks, KeyAction::Undo, &["U"]),
+    (KeyContext::Tasks, KeyAction::MoveUp, &["K"]),
+    (KeyContext::Tasks, KeyAction::MoveDown, &["J"]),
+    (KeyContext::Tasks, KeyAction::CycleScope, &["s"]),
+    (KeyContext::Tasks, KeyAction::MoveToList, &["M"]),
+    (KeyContext::Tasks, KeyAction::NewList, &["L"]),
+    (KeyContext::Archive, KeyAction::Restore, &["r"]),
+    (KeyContext::Archive, KeyAction::Purge, &["x"]),
+];
+
+/// A single key press, shift is folded into the character for printable keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+
+        KeyPress { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key_event: KeyEvent) -> Self {
+        KeyPress::new(key_event.code, key_event.modifiers)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub keys: Vec<KeyPress>,
+    // the notation from the config, used when displaying the binding
+    pub notation: String,
+    pub action: KeyAction,
+}
+
+#[derive(Debug)]
+pub struct KeymapError {
+    pub path: Option<PathBuf>,
+    pub message: String,
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(
+                f,
+                "invalid key bindings in {}: {}",
+                path.display(),
+                self.message
+            ),
+            None => write!(f, "invalid key bindings: {}", self.message),
+        }
+    }
+}
+
+pub enum KeyResult {
+    Action(KeyAction),
+    // the keys so far are the start of a longer sequence
+    Pending,
+    Unbound,
+}
+
+// a binding is either a single key sequence or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub struct Keymap {
+    bindings: BTreeMap<KeyContext, Vec<Binding>>,
+    pending: Vec<KeyPress>,
+}
+
+impl Keymap {
+    pub fn default_path() -> Option<PathBuf> {
+        crate::util::config_dir().map(|dir| dir.join("hourglass").join(KEYMAP_FILE_NAME))
+    }
+
+    /// The default bindings, overridden by the user's keys.toml when there is one
+    pub fn load() -> Result<Self, KeymapError> {
+        match Keymap::default_path() {
+            Some(path) if path.exists() => Keymap::from_file(&path),
+            _ => Keymap::parse(""),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, KeymapError> {
+        let content = fs::read_to_string(path).map_err(|err| KeymapError {
+            path: Some(path.to_path_buf()),
+            message: err.to_string(),
+        })?;
+
+        Keymap::parse(&content).map_err(|err| KeymapError {
+            path: Some(path.to_path_buf()),
+            ..err
+        })
+    }
+
+    /// Reads bindings in the form
+    ///
+    /// ```toml
+    /// [global]
+    /// first = "gg"
+    /// next = ["j", "<Down>", "<C-n>"]
+    /// ```
+    ///
+    /// binding an action replaces its default keys in that section
+    pub fn parse(content: &str) -> Result<Self, KeymapError> {
+        let error = |message: String| KeymapError {
+            path: None,
+            message,
+        };
+
+        let raw: BTreeMap<String, BTreeMap<String, RawBinding>> =
+            toml::from_str(content).map_err(|err| error(err.to_string()))?;
+
+        let mut configured: BTreeMap<(KeyContext, KeyAction), Vec<String>> = DEFAULT_BINDINGS
+            .iter()
+            .map(|(context, action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect();
+
+                ((*context, *action), keys)
+            })
+            .collect();
+
+        for (section, actions) in raw {
+            let context = KeyContext::from_name(&section).ok_or_else(|| {
+                error(format!(
+                    "unknown section [{}], expected one of {}",
+                    section,
+                    KeyContext::ALL
+                        .iter()
+                        .map(|context| context.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })?;
+
+            for (name, binding) in actions {
+                let action = KeyAction::from_name(&name)
+                    .ok_or_else(|| error(format!("unknown action `{}` in [{}]", name, section)))?;
+
+                let keys = match binding {
+                    RawBinding::One(key) => vec![key],
+                    RawBinding::Many(keys) => keys,
+                };
+
+                configured.insert((context, action), keys);
+            }
+        }
+
+        let mut bindings: BTreeMap<KeyContext, Vec<Binding>> = BTreeMap::new();
+
+        for ((context, action), notations) in configured {
+            for notation in notations {
+                let keys = parse_keys(&notation).map_err(|message| {
+                    error(format!(
+                        "`{}` for `{}` in [{}]: {}",
+                        notation,
+                        action.name(),
+                        context.name(),
+                        message
+                    ))
+                })?;
+
+                bindings.entry(context).or_default().push(Binding {
+                    keys,
+                    notation,
+                    action,
+                });
+            }
+        }
+
+        let keymap = Keymap {
+            bindings,
+            pending: vec![],
+        };
+
+        keymap.validate().map_err(error)?;
+
+        Ok(keymap)
+    }
+
+    // two bindings that can be active at the same time must not be equal or a prefix of each other,
+    // otherwise the second one could never be triggered
+    fn validate(&self) -> Result<(), String> {
+        // global bindings are checked as part of every view
+        for context in KeyContext::ALL
+            .into_iter()
+            .filter(|context| *context != KeyContext::Global)
+        {
+            let active = self.active_bindings(context);
+
+            for (i, a) in active.iter().enumerate() {
+                for b in active.iter().skip(i + 1) {
+                    let shortest = a.keys.len().min(b.keys.len());
+
+                    if a.keys[..shortest] == b.keys[..shortest] {
+                        return Err(format!(
+                            "`{}` ({}) conflicts with `{}` ({}) in the {} view",
+                            a.notation,
+                            a.action.name(),
+                            b.notation,
+                            b.action.name(),
+                            context.name()
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The bindings that apply in a view, view specific ones first
+    pub fn active_bindings(&self, context: KeyContext) -> Vec<&Binding> {
+        let specific = self.bindings.get(&context).into_iter().flatten();
+        let global = self.bindings.get(&KeyContext::Global).into_iter().flatten();
+
+        specific.chain(global).collect()
+    }
+
+    /// The keys bound to each action in one section only, in the order of `KeyAction::ALL`
+    pub fn bindings_by_action(&self, context: KeyContext) -> Vec<(KeyAction, Vec<&str>)> {
+        let bindings = self
+            .bindings
+            .get(&context)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        KeyAction::ALL
+            .iter()
+            .filter_map(|action| {
+                let notations: Vec<&str> = bindings
+                    .iter()
+                    .filter(|binding| binding.action == *action)
+                    .map(|binding| binding.notation.as_str())
+                    .collect();
+
+                (!notations.is_empty()).then_some((*action, notations))
+            })
+            .collect()
+    }
+
+    /// Feeds a key press, multi-key sequences like `gg` are resolved once complete
+    pub fn handle(&mut self, context: KeyContext, key_event: KeyEvent) -> KeyResult {
+        self.pending.push(KeyPress::from(key_event));
+
+        let mut is_prefix = false;
+
+        for binding in self.active_bindings(context) {
+            if binding.keys == self.pending {
+                let action = binding.action;
+                self.pending.clear();
+
+                return KeyResult::Action(action);
+            }
+
+            if binding.keys.starts_with(&self.pending) {
+                is_prefix = true;
+            }
+        }
+
+        if is_prefix {
+            return KeyResult::Pending;
+        }
+
+        // a key that doesn't continue the pending sequence starts over on its own, e.g. `gj`
+        // still moves down
+        if self.pending.len() > 1 {
+            self.pending.clear();
+
+            return self.handle(context, key_event);
+        }
+
+        self.pending.clear();
+
+        KeyResult::Unbound
+    }
+}
+
+// vim notation: plain characters, or special keys and modifiers between angle brackets,
+// e.g. `gg`, `<C-d>`, `<S-Tab>`, `<Space>`
+fn parse_keys(notation: &str) -> Result<Vec<KeyPress>, String> {
+    let chars: Vec<char> = notation.chars().collect();
+    let mut keys = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let closing = chars[i..].iter().position(|&c| c == '>');
+
+        match closing {
+            // a lone `<` or `<>` is just the character
+            Some(len) if chars[i] == '<' && len > 1 => {
+                let name: String = chars[i + 1..i + len].iter().collect();
+
+                keys.push(parse_special_key(&name)?);
+                i += len + 1;
+            }
+            _ => {
+                keys.push(KeyPress::new(KeyCode::Char(chars[i]), KeyModifiers::NONE));
+                i += 1;
+            }
+        }
+    }
+
+    if keys.is_empty() {
+        return Err(String::from("no keys given"));
+    }
+
+    Ok(keys)
+}
+
+fn parse_special_key(name: &str) -> Result<KeyPress, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+
+    // modifiers come first, e.g. `C-S-Left`
+    while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "c" | "ctrl" => KeyModifiers::CONTROL,
+            "a" | "m" | "alt" => KeyModifiers::ALT,
+            "s" | "shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier `{}`", modifier)),
+        };
+        rest = key;
+    }
+
+    let code = match rest.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+            modifiers -= KeyModifiers::SHIFT;
+            KeyCode::BackTab
+        }
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        key => {
+            let mut chars = key.chars();
+
+            match (chars.next(), chars.next()) {
+                // keep the case of the original character, `<C-a>` and `<A-A>` are different keys
+                (Some(_), None) => KeyCode::Char(rest.chars().next().unwrap_or_default()),
+                _ => match key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `<{}>`", name)),
+                },
+            }
+        }
+    };
+
+    // terminals send shifted characters as the character itself, shift is never seen with it
+    if let (KeyCode::Char(c), true) = (code, modifiers.contains(KeyModifiers::SHIFT)) {
+        return Err(format!(
+            "shift cannot be combined with `{}`, write the uppercase letter or the shifted character instead, e.g. `<C-A>`",
+            c
+        ));
+    }
+
+    Ok(KeyPress::new(code, modifiers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyPress {
+        KeyPress::new(code, KeyModifiers::NONE)
+    }
+
+    fn press(keymap: &mut Keymap, c: char) -> KeyResult {
+        keymap.handle(
+            KeyContext::Tasks,
+            KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+        )
+    }
+
+    #[test]
+    fn notation() {
+        let ctrl = |c| KeyPress::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+
+        assert_eq!(
+            parse_keys("gg"),
+            Ok(vec![key(KeyCode::Char('g')), key(KeyCode::Char('g'))])
+        );
+        assert_eq!(parse_keys("<C-d>"), Ok(vec![ctrl('d')]));
+        assert_eq!(parse_keys("<ctrl-A>"), Ok(vec![ctrl('A')]));
+        assert_eq!(parse_keys("<S-Tab>"), Ok(vec![key(KeyCode::BackTab)]));
+        assert_eq!(parse_keys("<Space>"), Ok(vec![key(KeyCode::Char(' '))]));
+        assert_eq!(parse_keys("<lt>"), Ok(vec![key(KeyCode::Char('<'))]));
+        assert_eq!(parse_keys("<"), Ok(vec![key(KeyCode::Char('<'))]));
+        assert_eq!(parse_keys("<F5>"), Ok(vec![key(KeyCode::F(5))]));
+        assert_eq!(
+            parse_keys("<S-Left>"),
+            Ok(vec![KeyPress::new(KeyCode::Left, KeyModifiers::SHIFT)])
+        );
+    }
+
+    #[test]
+    fn invalid_notation() {
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("<F13>").is_err());
+        assert!(parse_keys("<Nope>").is_err());
+        assert!(parse_keys("<X-a>").is_err());
+    }
+
+    #[test]
+    fn shift_with_a_character() {
+        for notation in ["<S-a>", "<C-S-a>", "<S-Space>"] {
+            let err = parse_keys(notation).unwrap_err();
+
+            assert!(err.contains("uppercase"), "{}: {}", notation, err);
+        }
+    }
+
+    #[test]
+    fn shifted_characters_match_their_key_events() {
+        let event = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+
+        assert_eq!(KeyPress::from(event), parse_keys("G").unwrap()[0]);
+    }
+
+    #[test]
+    fn bindings_replace_the_defaults() {
+        let keymap = Keymap::parse("[tasks]\nadd = [\"A\", \"<C-n>\"]").unwrap();
+        let bindings = keymap.bindings_by_action(KeyContext::Tasks);
+
+        let add = bindings
+            .iter()
+            .find(|(action, _)| *action == KeyAction::Add)
+            .unwrap();
+        assert_eq!(add.1, ["A", "<C-n>"]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        assert!(Keymap::parse("[nowhere]\nadd = \"a\"").is_err());
+        assert!(Keymap::parse("[tasks]\nfly = \"f\"").is_err());
+        assert!(Keymap::parse("[tasks]\nadd = \"<S-a>\"").is_err());
+    }
+
+    #[test]
+    fn conflicts() {
+        // the same key twice in one view
+        let err = Keymap::parse("[tasks]\nadd = \"d\"").err().unwrap();
+        assert!(err.message.contains("conflicts"), "{}", err);
+
+        // a task view key hiding a global one
+        assert!(Keymap::parse("[tasks]\nadd = \"j\"").is_err());
+
+        // `g` would always fire before `gx`
+        assert!(Keymap::parse("[tasks]\nadd = \"gx\"").is_err());
+
+        // views don't see each other's keys
+        assert!(Keymap::parse("[archive]\nrestore = \"a\"").is_ok());
+    }
+
+    #[test]
+    fn pending_sequences() {
+        let mut keymap = Keymap::parse("[global]\nfirst = \"gg\"").unwrap();
+
+        assert!(matches!(press(&mut keymap, 'g'), KeyResult::Pending));
+        assert!(matches!(
+            press(&mut keymap, 'g'),
+            KeyResult::Action(KeyAction::First)
+        ));
+
+        // a key that doesn't continue the sequence is handled on its own
+        assert!(matches!(press(&mut keymap, 'g'), KeyResult::Pending));
+        assert!(matches!(
+            press(&mut keymap, 'j'),
+            KeyResult::Action(KeyAction::Next)
+        ));
+
+        assert!(matches!(press(&mut keymap, 'g'), KeyResult::Pending));
+        assert!(matches!(press(&mut keymap, 'z'), KeyResult::Unbound));
+        assert!(matches!(
+            press(&mut keymap, 'j'),
+            KeyResult::Action(KeyAction::Next)
+        ));
+    }
+}
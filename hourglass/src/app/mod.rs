@@ -1,34 +1,107 @@
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, widgets::TableState, Terminal};
+use ratatui::{backend::CrosstermBackend, layout::Rect, widgets::TableState, Terminal};
 use rustyline::line_buffer::DeleteListener;
 use rustyline::line_buffer::Direction;
 use rustyline::line_buffer::{ChangeListener, LineBuffer};
-use serde::{Deserialize, Serialize};
-use serde_json;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
+use std::path::Path;
+use std::process;
+use std::thread;
 use std::{
-    env, io,
+    cmp, env,
+    io::{self, Write},
     time::{Duration, Instant},
 };
 
 mod action;
 mod cache;
+mod caldav;
+mod command;
+mod completion;
+pub mod config;
+pub mod formats;
+mod history;
+mod input;
 mod issues;
+pub mod keymap;
 pub mod scheduler;
+pub mod storage;
+mod task;
 mod ui;
 
-use crate::app::cache::ISSUES_CACHE;
+use crate::app::cache::{COMMENTS_CACHE, ISSUES_CACHE};
 use action::Action;
-use issues::{get_issues, RepoIssue};
+use caldav::{SyncResult, SyncState};
+use command::{Command, Filter, Modification, Selection, SortKey};
+use completion::{complete, Completion};
+use config::{Config, KNOWN_TABS};
+use formats::{ExportOptions, Format};
+use history::History;
+use issues::{cached_issue_comments, get_issue_comments, get_issues, IssueComment, RepoIssue};
+use keymap::{KeyAction, KeyContext, KeyResult, Keymap};
 use scheduler::{Scheduler, TimeUnits};
+use storage::{LocatedStore, Location, Scope, SqliteStore, Store};
+use task::{parse_input, Task};
+use ui::theme::Theme;
+use ui::LayoutMode;
 
 const MAX_LINE_CAPACITY: usize = 4096;
 
+// how much of the main area the table takes, the details pane gets the rest
+const DEFAULT_SPLIT_PERCENT: u16 = 50;
+const MIN_SPLIT_PERCENT: u16 = 10;
+const MAX_SPLIT_PERCENT: u16 = 90;
+const SPLIT_STEP: u16 = 5;
+
+// two clicks on the same row within this delay make a double click
+const DOUBLE_CLICK_DELAY: Duration = Duration::from_millis(400);
+const WHEEL_SCROLL_LINES: u16 = 3;
+
+const MAX_UNDO_STEPS: usize = 100;
+
+// the comments of an issue are fetched once it stays selected this long, not for every issue
+// j or k goes past
+const COMMENTS_DELAY: Duration = Duration::from_millis(300);
+
+/// What the keys handled by the command line itself do, anything else edits the line
+#[derive(Clone, Copy)]
+enum CommandLineKey {
+    Submit,
+    Cancel,
+    Complete,
+    HistoryPrevious,
+    HistoryNext,
+}
+
+// looked up by `update_command_input` and shown as hints while typing, keys next to each
+// other with the same description share a hint
+const COMMAND_LINE_KEYS: [(KeyCode, &str, &str, CommandLineKey); 5] = [
+    (KeyCode::Enter, "Enter", "submit", CommandLineKey::Submit),
+    (KeyCode::Esc, "Esc", "cancel", CommandLineKey::Cancel),
+    (KeyCode::Tab, "Tab", "complete", CommandLineKey::Complete),
+    (
+        KeyCode::Up,
+        "Up",
+        "history",
+        CommandLineKey::HistoryPrevious,
+    ),
+    (
+        KeyCode::Down,
+        "Down",
+        "history",
+        CommandLineKey::HistoryNext,
+    ),
+];
+
 /// Undo manager
 #[derive(Default)]
 pub struct Changeset {}
@@ -48,52 +121,174 @@ impl ChangeListener for Changeset {
 enum View {
     Task(Action),
     Issues(Action),
+    Archive(Action),
+}
+
+/// Feedback for the last command, shown in the command bar until the next key press
+enum Message {
+    Info(String),
+    Error(String),
+}
+
+impl Message {
+    // both texts, an error when either one is
+    fn and(self, other: Message) -> Message {
+        match (self, other) {
+            (Message::Info(a), Message::Info(b)) => Message::Info(format!("{}; {}", a, b)),
+            (Message::Error(a), Message::Info(b) | Message::Error(b))
+            | (Message::Info(a), Message::Error(b)) => Message::Error(format!("{}; {}", a, b)),
+        }
+    }
+}
+
+/// Which pane receives the navigation keys
+#[derive(PartialEq)]
+enum Focus {
+    Table,
+    Details,
 }
 
 pub const HOURGLASS_EXTENSION: &str = "hourglass";
-pub const HOURGLASS_FILE_STORAGE_NAME: &str = "tasks.hourglass";
-pub const TIME_FORMAT: &'static str = "%b %d, %Y %I:%M %p";
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-struct Task {
-    id: i32,
-    description: String,
-    completed: bool,
-    created_at: DateTime<Utc>,
-    modified_at: DateTime<Utc>,
+
+// the default list goes by the name of its tab
+const DEFAULT_LIST: &str = "tasks";
+
+/// Where things were drawn on the last frame, filled in by `build_ui` so mouse events can be
+/// mapped back to what is under the cursor
+#[derive(Default)]
+struct Areas {
+    // one per tab title, including the padding around it
+    tabs: Vec<Rect>,
+    // the rows of the table, without the header
+    table_rows: Rect,
+    details: Rect,
 }
 
 pub struct Hourglass {
+    config: Config,
+    theme: Theme,
     command_input: LineBuffer,
     changes: Changeset,
+    keymap: Keymap,
+    history: History,
+    // the completion popup, only present while it is open
+    completion: Option<Completion>,
     next_id: i32,
     view: View,
     table_state: TableState,
+    // the tabs of the config, with one for each named list after the tasks tab
     tabs: Vec<String>,
     tab_index: usize,
     tasks: Vec<Task>,
+    // the named list the tasks tab shows, `None` for the default one
+    list: Option<String>,
+    // ids of the tasks marked for a bulk operation, commands without ids apply to them
+    marked: BTreeSet<i32>,
+    // the last task marked, where `mark_range` starts from
+    mark_anchor: Option<i32>,
+    // completed tasks moved out of the way, most recently completed first
+    archive: Vec<Task>,
+    // where `tasks` and `archive` are loaded from and saved to, each task says which one
+    stores: Vec<LocatedStore>,
+    // which of those the tasks tab shows
+    scope: Scope,
+    // the tasks and the archive before each change, a bulk operation is a single step
+    undo_stack: Vec<(Vec<Task>, Vec<Task>)>,
+    sort_key: Option<SortKey>,
+    filter: Option<Filter>,
+    message: Option<Message>,
+    // what merging the changes made outside hourglass did, shown once the command that
+    // saved is done
+    external_changes: Option<Message>,
     issues: Vec<RepoIssue>,
+    // comments of the currently selected issue
+    comments: Vec<IssueComment>,
+    // the selected issue while its comments are still to be fetched, they show up once
+    // fetched in the background
+    comments_issue: Option<u32>,
+    comments_selected_at: Instant,
+    // the fetches in flight, at most one per issue
+    comments_fetches: BTreeMap<u32, thread::JoinHandle<Option<Vec<IssueComment>>>>,
+    focus: Focus,
+    // follows the terminal width, see `handle_resize`
+    layout: LayoutMode,
+    split_percent: u16,
+    details_scroll: u16,
+    // both are filled in while rendering so that scrolling can be clamped
+    details_height: u16,
+    details_content_height: u16,
+    show_help: bool,
+    areas: Areas,
+    // when and where the last left click happened, to detect double clicks
+    last_click: Option<(Instant, u16)>,
+    // clamped while rendering, like `details_scroll`
+    help_scroll: u16,
     should_quit: bool,
+    // set by the key handler, the editor is opened from `run` because it needs the terminal
+    should_edit_notes: bool,
     is_issues_scheduler_running: bool,
 }
 
 impl Hourglass {
-    pub fn new() -> Self {
+    pub fn new(config: Config, keymap: Keymap) -> Self {
         let mut table_state = TableState::default();
 
         table_state.select(Some(0));
 
+        let tabs = config.tabs.clone();
+
+        // already validated when the config was loaded
+        let theme = config.resolve_theme().unwrap_or_default();
+        let stores = storage::locate(&config);
+
+        // there is no repo to show outside of one
+        let scope = match config.scope {
+            Scope::Repo if !stores.iter().any(|s| s.location == Location::Repo) => Scope::All,
+            scope => scope,
+        };
+
         Self {
+            config,
+            theme,
             should_quit: false,
+            should_edit_notes: false,
             is_issues_scheduler_running: false,
             command_input: LineBuffer::with_capacity(MAX_LINE_CAPACITY),
             changes: Changeset::default(),
+            keymap,
+            history: History::load(),
+            completion: None,
             view: View::Task(Action::View),
             next_id: 1,
             tasks: vec![],
+            list: None,
+            marked: BTreeSet::new(),
+            mark_anchor: None,
+            archive: vec![],
+            stores,
+            scope,
+            undo_stack: vec![],
+            sort_key: None,
+            filter: None,
+            message: None,
+            external_changes: None,
             issues: vec![],
+            comments: vec![],
+            comments_issue: None,
+            comments_selected_at: Instant::now(),
+            comments_fetches: BTreeMap::new(),
+            focus: Focus::Table,
+            layout: LayoutMode::Stacked,
+            split_percent: DEFAULT_SPLIT_PERCENT,
+            details_scroll: 0,
+            details_height: 0,
+            details_content_height: 0,
+            show_help: false,
+            areas: Areas::default(),
+            last_click: None,
+            help_scroll: 0,
             table_state,
-            tabs: vec![String::from("tasks"), String::from("issues")],
+            tabs,
             tab_index: 0,
         }
     }
@@ -101,7 +296,12 @@ impl Hourglass {
     pub fn start_tui() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )?;
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
@@ -112,7 +312,12 @@ impl Hourglass {
         let backend = CrosstermBackend::new(io::stdout());
         let mut terminal = Terminal::new(backend)?;
         disable_raw_mode()?;
-        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
         terminal.show_cursor()?;
         Ok(())
     }
@@ -122,7 +327,7 @@ impl Hourglass {
         terminal: &mut Terminal<B>,
     ) -> io::Result<()> {
         let mut last_tick = Instant::now();
-        let tick_rate = Duration::from_millis(250);
+        let tick_rate = Duration::from_millis(self.config.tick_rate_ms);
         // how is rust able to run an infinite loop without crashing?
 
         let mut scheduler = Scheduler::new();
@@ -132,15 +337,19 @@ impl Hourglass {
                 .run(|| {
                     // clear the cache
                     ISSUES_CACHE.lock().unwrap().remove("issues");
+                    COMMENTS_CACHE.lock().unwrap().clear();
 
                     // fetch issues again and refresh the cache
                     get_issues();
                 })
-                .every(30.seconds());
+                .every(self.config.issues_refresh_secs.seconds());
 
             self.is_issues_scheduler_running = true;
         }
 
+        let size = terminal.size()?;
+        self.handle_resize(size.width);
+
         loop {
             terminal.draw(|f| {
                 ui::build_ui(f, self);
@@ -155,11 +364,20 @@ impl Hourglass {
 
             // the poll method will halt the loop to wait a certain amount of time (based on timeout) for an event to occur before moving on
             if crossterm::event::poll(timeout)? {
-                if let Event::Key(key) = event::read()? {
-                    self.handle_input(key);
+                match event::read()? {
+                    Event::Key(key) => self.handle_input(key),
+                    Event::Paste(text) => self.handle_paste(&text),
+                    Event::Mouse(mouse_event) => self.handle_mouse(mouse_event),
+                    Event::Resize(width, _) => self.handle_resize(width),
+                    _ => {}
                 }
             }
 
+            if self.should_edit_notes {
+                self.should_edit_notes = false;
+                self.edit_notes(terminal)?;
+            }
+
             if self.should_quit {
                 return Ok(());
             }
@@ -168,18 +386,64 @@ impl Hourglass {
             // without this line, the program will consume very high CPU
             if last_tick.elapsed() >= tick_rate {
                 last_tick = Instant::now();
+
+                self.poll_comments();
+                self.reload_changed_stores();
+            }
+        }
+    }
+
+    fn row_count(&self) -> usize {
+        match self.view {
+            View::Task(_) => self.visible_tasks().len(),
+            View::Issues(_) => self.issues.len(),
+            View::Archive(_) => self.archive.len(),
+        }
+    }
+
+    /// Indices into `self.tasks` of the rows shown in the table, with the filter and sort applied
+    fn visible_tasks(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.tasks.len())
+            .filter(|&i| self.tasks[i].list == self.list)
+            .filter(|&i| self.scope.includes(self.tasks[i].location))
+            .filter(|&i| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(&self.tasks[i]))
+            })
+            .collect();
+
+        self.sort_indices(&mut indices);
+
+        indices
+    }
+
+    fn sort_indices(&self, indices: &mut [usize]) {
+        match self.sort_key {
+            Some(sort_key) => {
+                indices.sort_by(|&a, &b| sort_key.compare(&self.tasks[a], &self.tasks[b]))
             }
+            // each store has its own manual order, the repo tasks come first
+            None => indices.sort_by_key(|&i| (self.tasks[i].location, self.tasks[i].position)),
         }
     }
 
+    // index into `self.tasks` of the task under the cursor
+    fn selected_task_index(&self) -> Option<usize> {
+        let row = self.table_state.selected()?;
+
+        self.visible_tasks().get(row).copied()
+    }
+
     fn next(&mut self) {
+        let len = self.row_count();
+
+        if len == 0 {
+            return;
+        }
+
         let i = match self.table_state.selected() {
             Some(i) => {
-                let len = match self.view {
-                    View::Task(_) => self.tasks.len(),
-                    View::Issues(_) => self.issues.len(),
-                };
-
                 if i >= len - 1 {
                     0
                 } else {
@@ -189,17 +453,19 @@ impl Hourglass {
             None => 0,
         };
 
-        self.table_state.select(Some(i))
+        self.table_state.select(Some(i));
+        self.on_selection_changed();
     }
 
     fn previous(&mut self) {
+        let len = self.row_count();
+
+        if len == 0 {
+            return;
+        }
+
         let i = match self.table_state.selected() {
             Some(i) => {
-                let len = match self.view {
-                    View::Task(_) => self.tasks.len(),
-                    View::Issues(_) => self.issues.len(),
-                };
-
                 if i == 0 {
                     len - 1
                 } else {
@@ -209,6 +475,96 @@ impl Hourglass {
             None => 0,
         };
         self.table_state.select(Some(i));
+        self.on_selection_changed();
+    }
+
+    fn select_row(&mut self, row: usize) {
+        self.table_state.select(Some(row));
+        self.on_selection_changed();
+    }
+
+    fn on_selection_changed(&mut self) {
+        self.details_scroll = 0;
+
+        if let View::Issues(_) = self.view {
+            self.load_comments();
+        }
+    }
+
+    fn load_comments(&mut self) {
+        let number = self
+            .table_state
+            .selected()
+            .and_then(|i| self.issues.get(i))
+            .map(|issue| issue.number);
+
+        let cached = number.and_then(cached_issue_comments);
+
+        // a request on every j or k would freeze the UI, the tick fetches them
+        self.comments_issue = number.filter(|_| cached.is_none());
+        self.comments = cached.unwrap_or_default();
+        self.comments_selected_at = Instant::now();
+    }
+
+    // picks up the fetched comments, and starts fetching those of an issue that stayed
+    // selected long enough. A failed fetch isn't retried until the issue is selected again
+    fn poll_comments(&mut self) {
+        let finished: Vec<u32> = self
+            .comments_fetches
+            .iter()
+            .filter(|(_, fetch)| fetch.is_finished())
+            .map(|(number, _)| *number)
+            .collect();
+
+        for number in finished {
+            let comments = self
+                .comments_fetches
+                .remove(&number)
+                .and_then(|fetch| fetch.join().ok())
+                .flatten();
+
+            if self.comments_issue == Some(number) {
+                self.comments = comments.unwrap_or_default();
+                self.comments_issue = None;
+            }
+        }
+
+        if let Some(number) = self.comments_issue {
+            if !self.comments_fetches.contains_key(&number)
+                && self.comments_selected_at.elapsed() >= COMMENTS_DELAY
+            {
+                let fetch = thread::spawn(move || get_issue_comments(number));
+
+                self.comments_fetches.insert(number, fetch);
+            }
+        }
+    }
+
+    fn scroll_details_down(&mut self, amount: u16) {
+        let max_scroll = self
+            .details_content_height
+            .saturating_sub(self.details_height);
+
+        self.details_scroll = cmp::min(self.details_scroll.saturating_add(amount), max_scroll);
+    }
+
+    fn scroll_details_up(&mut self, amount: u16) {
+        self.details_scroll = self.details_scroll.saturating_sub(amount);
+    }
+
+    fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Table => Focus::Details,
+            Focus::Details => Focus::Table,
+        };
+    }
+
+    fn grow_table(&mut self) {
+        self.split_percent = cmp::min(self.split_percent + SPLIT_STEP, MAX_SPLIT_PERCENT);
+    }
+
+    fn shrink_table(&mut self) {
+        self.split_percent = cmp::max(self.split_percent - SPLIT_STEP, MIN_SPLIT_PERCENT);
     }
 
     fn next_tab(&mut self) {
@@ -228,8 +584,11 @@ impl Hourglass {
     }
 
     fn update_view(&mut self) {
+        self.list = None;
+
         match self.tabs[self.tab_index].as_str() {
             "tasks" => self.view = View::Task(Action::View),
+            "archive" => self.view = View::Archive(Action::View),
             "issues" => {
                 self.view = View::Issues(Action::View);
 
@@ -240,188 +599,1663 @@ impl Hourglass {
 
                 self.issues = issues;
             }
-            _ => {}
+            // the other tabs are named lists
+            list => {
+                self.view = View::Task(Action::View);
+                self.list = Some(list.to_string());
+            }
         }
 
         self.table_state = TableState::default();
 
         self.table_state.select(Some(0));
+        self.on_selection_changed();
     }
 
-    fn toggle_task_status(&mut self) {
-        if let Some(i) = self.table_state.selected() {
-            if let Some(task) = self.tasks.get_mut(i) {
-                task.completed = !task.completed;
+    // keeps the cursor on a row that still exists after the table shrank
+    fn clamp_selection(&mut self) {
+        let len = self.row_count();
+
+        match self.table_state.selected() {
+            Some(i) if i >= len => self.table_state.select(Some(len.saturating_sub(1))),
+            None => self.table_state.select(Some(0)),
+            _ => {}
+        }
+    }
+
+    fn switch_to_tab(&mut self, name: &str) -> bool {
+        match self.tabs.iter().position(|tab| tab == name) {
+            Some(index) => {
+                self.tab_index = index;
+                self.update_view();
+
+                true
             }
+            None => false,
         }
     }
 
-    fn add_task(&mut self) {
-        let description = self.command_input.as_str().to_string();
-        let time = Utc::now();
+    // indices into `self.tasks` of the tasks a command applies to, only the ones shown so
+    // ids can't reach into other lists, scopes or filtered out tasks
+    fn resolve_selection(&self, selection: &Selection) -> Vec<usize> {
+        match selection {
+            // marked tasks hidden by the filter are left alone
+            Selection::Current if !self.marked.is_empty() => self
+                .visible_tasks()
+                .into_iter()
+                .filter(|&i| self.marked.contains(&self.tasks[i].id))
+                .collect(),
+            Selection::Current => self.selected_task_index().into_iter().collect(),
+            Selection::Ids(_) => self
+                .visible_tasks()
+                .into_iter()
+                .filter(|&i| selection.contains(self.tasks[i].id))
+                .collect(),
+        }
+    }
 
-        self.tasks.push(Task {
-            id: self.next_id,
-            description,
-            completed: false,
-            created_at: time,
-            modified_at: time,
-        });
+    fn execute(&mut self, command: Command) {
+        let message = match self.apply_command(command) {
+            Ok(text) if text.is_empty() => None,
+            Ok(text) => Some(Message::Info(text)),
+            Err(text) => Some(Message::Error(text)),
+        };
 
-        self.next_id += 1;
-        self.save_tasks();
-        self.clear_command();
+        // the command's own result comes first, it is what was just asked for
+        self.message = match (message, self.external_changes.take()) {
+            (Some(message), Some(external)) => Some(message.and(external)),
+            (message, external) => message.or(external),
+        };
     }
 
-    fn update_task(&mut self) {
-        if let Some(i) = self.table_state.selected() {
-            if let Some(task) = self.tasks.get_mut(i) {
-                task.description = self.command_input.as_str().to_string();
+    // what to tell about the command, `execute` shows it
+    fn apply_command(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Add(input) => {
+                self.checkpoint();
+
+                // new tasks go at the bottom of the manual order
+                let mut task = Task::new(self.next_id, parse_input(&input));
+                task.location = self.new_task_location();
+                task.list = self.list.clone();
+                task.position = self
+                    .tasks
+                    .iter()
+                    .map(|task| task.position + 1)
+                    .max()
+                    .unwrap_or(0);
+
+                self.tasks.push(task);
+                self.next_id += 1;
+                self.save()?;
+
+                Ok(format!("added task {}", self.next_id - 1))
+            }
+            Command::Done(selection) => self.set_completed(&selection, |_| true),
+            Command::Undone(selection) => self.set_completed(&selection, |_| false),
+            Command::Toggle(selection) => self.set_completed(&selection, |completed| !completed),
+            Command::Delete(selection) => {
+                let mut indices = self.resolve_selection(&selection);
+
+                if indices.is_empty() {
+                    return Err(String::from("no matching task"));
+                }
 
-                task.modified_at = Utc::now();
+                self.checkpoint();
+
+                // remove from the back so the remaining indices stay valid
+                indices.sort_unstable();
+                for &i in indices.iter().rev() {
+                    self.tasks.remove(i);
+                }
+
+                self.clamp_selection();
+                self.clear_marks_used_by(&selection);
+                self.save()?;
+
+                Ok(format!("deleted {} task(s)", indices.len()))
+            }
+            Command::Modify(selection, modification) => self.modify(&selection, &modification),
+            Command::Move(selection, list) => self.move_to_list(&selection, &list),
+            Command::Archive => {
+                if self.tasks.iter().any(|task| task.completed) {
+                    self.checkpoint();
+
+                    let count = self.archive_completed(None);
+                    self.clamp_selection();
+                    let count = count?;
+
+                    Ok(format!("archived {} task(s)", count))
+                } else {
+                    Err(String::from("no completed task to archive"))
+                }
+            }
+            Command::Restore(selection) => self.restore(&selection),
+            Command::Purge(selection) => {
+                let indices = self.resolve_archive_selection(&selection);
+
+                if indices.is_empty() {
+                    Err(String::from("no matching archived task"))
+                } else {
+                    self.checkpoint();
+
+                    for &i in indices.iter().rev() {
+                        self.archive.remove(i);
+                    }
+
+                    self.clamp_selection();
+                    self.save()?;
+
+                    Ok(format!("purged {} task(s)", indices.len()))
+                }
+            }
+            Command::Undo => self.undo(),
+            Command::Sort(sort_key) => {
+                self.sort_key = sort_key;
+
+                Ok(match sort_key {
+                    Some(sort_key) => format!("sorted by {}", sort_key.as_str()),
+                    None => String::from("sorted manually"),
+                })
+            }
+            Command::Filter(filter) => {
+                self.filter = filter;
+                self.table_state.select(Some(0));
+
+                Ok(format!("{} task(s) shown", self.visible_tasks().len()))
+            }
+            Command::Tab(name) => {
+                if self.switch_to_tab(&name) {
+                    Ok(String::new())
+                } else {
+                    Err(format!("no tab named `{}`", name))
+                }
+            }
+            Command::Export(format, path) => {
+                fs::write(&path, self.export(format, &ExportOptions::default()))
+                    .map(|_| {
+                        format!(
+                            "exported {} task(s) to {}",
+                            self.visible_tasks().len(),
+                            path.display()
+                        )
+                    })
+                    .map_err(|err| format!("unable to export: {}", err))
+            }
+            Command::Scope(scope) => self.set_scope(scope),
+            Command::List(name) => self.open_list(&name),
+            Command::Import(format, path) => self.import(format, &path),
+            Command::Sync => self.sync().map(|result| {
+                // the first conflict gives an idea, `hourglass sync` lists all of them
+                match result.conflicts.first() {
+                    Some(conflict) => format!("{} ({})", result.summary(), conflict),
+                    None => result.summary(),
+                }
+            }),
+            Command::Write => {
+                self.save()?;
+
+                Ok(format!("written {} task(s)", self.tasks.len()))
+            }
+            Command::Quit => {
+                self.should_quit = true;
 
-                self.save_tasks();
+                Ok(String::new())
+            }
+            Command::WriteQuit => {
+                self.save()?;
+                self.should_quit = true;
+
+                Ok(String::new())
             }
         }
-        self.clear_command();
     }
 
-    fn remove_task(&mut self) {
-        if let Some(index) = self.table_state.selected() {
-            if index < self.tasks.len() {
-                self.tasks.remove(index);
-                self.save_tasks();
+    /// The tasks shown in the tasks tab, filtered and sorted the same way
+    pub fn export(&self, format: Format, options: &ExportOptions) -> String {
+        let visible = self.visible_tasks();
+        let tasks: Vec<&Task> = visible.iter().map(|&i| &self.tasks[i]).collect();
+
+        formats::export(&tasks, format, options)
+    }
+
+    /// The tasks matching the same filter as `:filter`, queried from the store so the
+    /// SQLite backend can use its indexes
+    pub fn export_matching(
+        &self,
+        format: Format,
+        filter: &str,
+        options: &ExportOptions,
+    ) -> Result<String, String> {
+        let args: Vec<&str> = filter.split_whitespace().collect();
+        let filter = Filter::parse(&args).map_err(|err| err.to_string())?;
+        let mut matching = BTreeSet::new();
+
+        for located in self.stores.iter() {
+            if self.scope.includes(located.location) {
+                matching.extend(located.store.query(&filter)?.iter().map(|task| task.uuid));
             }
         }
+
+        // the loaded tasks are exported, they have the ids the tasks tab shows
+        let mut indices: Vec<usize> = (0..self.tasks.len())
+            .filter(|&i| self.tasks[i].list == self.list)
+            .filter(|&i| matching.contains(&self.tasks[i].uuid))
+            .collect();
+
+        self.sort_indices(&mut indices);
+
+        let tasks: Vec<&Task> = indices.iter().map(|&i| &self.tasks[i]).collect();
+
+        Ok(formats::export(&tasks, format, options))
     }
 
-    fn clear_command(&mut self) {
-        self.command_input.update("", 0, &mut self.changes);
+    /// Copies the task list and the archive of each store into a SQLite database next to
+    /// it, which must not have tasks yet
+    pub fn migrate(&self) -> Result<String, String> {
+        let mut databases = vec![];
+
+        for located in self.stores.iter() {
+            let path = located.dir.join(&self.config.database_file);
+            let mut database = SqliteStore::new(path.clone());
+            let (tasks, archive) = database.load()?;
+
+            if !tasks.is_empty() || !archive.is_empty() {
+                return Err(format!("{} already has tasks", path.display()));
+            }
+
+            databases.push((located.location, located.store.lists(), path, database));
+        }
+
+        let mut moved = vec![];
+
+        for (location, lists, path, mut database) in databases {
+            let (tasks, archive) = stored_tasks_in(&self.tasks, &self.archive, location);
+
+            if !tasks.is_empty() || !archive.is_empty() {
+                database.save(
+                    &tasks.iter().collect::<Vec<_>>(),
+                    &archive.iter().collect::<Vec<_>>(),
+                )?;
+
+                // the empty ones too
+                for list in lists {
+                    database.create_list(&list)?;
+                }
+
+                moved.push(format!(
+                    "{} task(s) and {} archived task(s) moved to {}",
+                    tasks.len(),
+                    archive.len(),
+                    path.display()
+                ));
+            }
+        }
+
+        match moved.is_empty() {
+            true => Err(String::from("no task to migrate")),
+            false => Ok(moved.join("\n")),
+        }
     }
 
-    fn handle_input(&mut self, key_event: KeyEvent) {
-        // we handle input differently based on the current view
-        match &self.view {
-            View::Task(action) => match action {
-                Action::View => self.handle_key_for_task_view(key_event.code),
-                _ => self.update_command_input(key_event.code),
-            },
+    /// Adds the tasks of a file at the bottom of the list, with new ids.
+    /// Tasks already in the list or the archive, going by their uuid, are updated instead
+    pub fn import(&mut self, format: Format, path: &Path) -> Result<String, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("unable to read {}: {}", path.display(), err))?;
 
-            View::Issues(action) => match action {
-                Action::View => self.handle_key_for_issues_view(key_event.code),
-                _ => self.update_command_input(key_event.code),
-            },
+        let imported = formats::import(&content, format)
+            .map_err(|err| format!("unable to import {}: {}", path.display(), err))?;
+
+        if imported.is_empty() {
+            return Err(format!("no task found in {}", path.display()));
         }
+
+        self.checkpoint();
+
+        let location = self.new_task_location();
+        let (added, updated) = self.merge_tasks(imported, location)?;
+
+        Ok(format!(
+            "{} task(s) added and {} updated from {}",
+            added,
+            updated,
+            path.display()
+        ))
     }
 
-    fn update_command_input(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char(c) => {
-                self.command_input.insert(c, 1, &mut self.changes);
+    /// Syncs the tasks and the archive of the global store with the CalDAV collection of the
+    /// config
+    pub fn sync(&mut self) -> Result<SyncResult, String> {
+        // the state is kept next to the global tasks and only they are synced, the tasks of a
+        // repo would otherwise look deleted whenever hourglass runs outside of it
+        let location = self.stores[0].location;
+        let path = self.stores[0].dir.join(&self.config.caldav.state_file);
+        let mut state = SyncState::load(&path)?;
+
+        let local: Vec<&Task> = self
+            .tasks
+            .iter()
+            .chain(self.archive.iter())
+            .filter(|task| task.location == location)
+            .collect();
+        let result = caldav::sync(&self.config.caldav, &mut state, &local);
+
+        // what was done before an error is remembered too
+        state.save(&path)?;
+        let result = result?;
+
+        let deleted: BTreeSet<_> = result.deleted.iter().collect();
+        let kept = |task: &Task| task.location != location || !deleted.contains(&task.uuid);
+
+        self.tasks.retain(kept);
+        self.archive.retain(kept);
+        self.clamp_selection();
+        self.merge_tasks(result.pulled.clone(), location)?;
+
+        Ok(result)
+    }
+
+    // adds the tasks at the bottom of the list of `location`, or replaces the ones with the
+    // same uuid, returns how many were added and updated
+    fn merge_tasks(
+        &mut self,
+        tasks: Vec<Task>,
+        location: Location,
+    ) -> Result<(usize, usize), String> {
+        let mut added = 0;
+        let mut updated = 0;
+        let mut position = self
+            .tasks
+            .iter()
+            .map(|task| task.position + 1)
+            .max()
+            .unwrap_or(0);
+
+        for mut task in tasks {
+            let existing = self
+                .tasks
+                .iter_mut()
+                .chain(self.archive.iter_mut())
+                .find(|t| t.uuid == task.uuid);
+
+            match existing {
+                Some(existing) => {
+                    task.id = existing.id;
+                    task.stored_id = existing.stored_id;
+                    task.location = existing.location;
+                    task.list = existing.list.clone();
+                    task.position = existing.position;
+                    *existing = task;
+                    updated += 1;
+                }
+                None => {
+                    task.id = self.next_id;
+                    task.location = location;
+                    task.list = self.list.clone();
+                    task.position = position;
+                    self.tasks.push(task);
+                    self.next_id += 1;
+                    position += 1;
+                    added += 1;
+                }
             }
-            KeyCode::Enter => match &self.view {
-                View::Task(action) => match action {
-                    Action::Add => {
-                        self.add_task();
+        }
 
-                        self.view = View::Task(Action::View);
-                    }
-                    Action::Update => {
-                        self.update_task();
+        self.save()?;
 
-                        self.view = View::Task(Action::View);
-                    }
-                    _ => {}
-                },
+        Ok((added, updated))
+    }
 
-                View::Issues(_action) => {}
-            },
-            KeyCode::Backspace => {
-                self.command_input.backspace(1, &mut self.changes);
-            }
-            KeyCode::Esc => {
-                self.clear_command();
-                self.view = View::Task(Action::View);
-            }
-            _ => {}
+    fn set_completed<F: Fn(bool) -> bool>(
+        &mut self,
+        selection: &Selection,
+        completed: F,
+    ) -> Result<String, String> {
+        let indices = self.resolve_selection(selection);
+
+        if indices.is_empty() {
+            return Err(String::from("no matching task"));
+        }
+
+        self.checkpoint();
+
+        for &i in indices.iter() {
+            let task = &mut self.tasks[i];
+
+            task.set_completed(completed(task.completed));
+        }
+
+        self.clear_marks_used_by(selection);
+        self.save()?;
+
+        Ok(format!("updated {} task(s)", indices.len()))
+    }
+
+    fn modify(
+        &mut self,
+        selection: &Selection,
+        modification: &Modification,
+    ) -> Result<String, String> {
+        let indices = self.resolve_selection(selection);
+
+        if indices.is_empty() {
+            return Err(String::from("no matching task"));
+        }
+
+        self.checkpoint();
+
+        for &i in indices.iter() {
+            modification.apply(&mut self.tasks[i]);
         }
+
+        self.clear_marks_used_by(selection);
+        self.save()?;
+
+        Ok(format!("modified {} task(s)", indices.len()))
     }
 
-    fn handle_key_for_task_view(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char(c) => match c {
-                'q' => self.should_quit = true,
-                'j' => self.next(),
-                'k' => self.previous(),
-                'd' => self.toggle_task_status(),
-                'a' => self.view = View::Task(Action::Add),
-                'u' => self.view = View::Task(Action::Update),
-                'x' => self.remove_task(),
-                ']' => self.next_tab(),
-                '[' => self.previous_tab(),
-                _ => {}
-            },
-            KeyCode::Down => self.next(),
-            KeyCode::Up => self.previous(),
-            _ => {}
+    // saves the task list so the change about to be made can be undone
+    fn checkpoint(&mut self) {
+        self.undo_stack
+            .push((self.tasks.clone(), self.archive.clone()));
+
+        if self.undo_stack.len() > MAX_UNDO_STEPS {
+            self.undo_stack.remove(0);
         }
     }
 
-    fn handle_key_for_issues_view(&mut self, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char(c) => match c {
-                'q' => self.should_quit = true,
-                'j' => self.next(),
-                'k' => self.previous(),
-                ']' => self.next_tab(),
-                '[' => self.previous_tab(),
-                _ => {}
+    fn undo(&mut self) -> Result<String, String> {
+        let (tasks, archive) = self
+            .undo_stack
+            .pop()
+            .ok_or_else(|| String::from("nothing to undo"))?;
+
+        self.tasks = tasks;
+        self.archive = archive;
+        self.clamp_selection();
+        self.save()?;
+
+        Ok(format!("undone, {} change(s) left", self.undo_stack.len()))
+    }
+
+    /// Moves completed tasks done for at least `min_age` to the archive, all of them without one
+    fn archive_completed(&mut self, min_age: Option<chrono::Duration>) -> Result<usize, String> {
+        let now = Utc::now();
+
+        let (archived, kept): (Vec<Task>, Vec<Task>) = self.tasks.drain(..).partition(|task| {
+            // tasks completed before `completed_at` existed only have `modified_at`
+            let completed_at = task.completed_at.unwrap_or(task.modified_at);
+
+            task.completed && min_age.is_none_or(|min_age| now - completed_at >= min_age)
+        });
+
+        self.tasks = kept;
+
+        let count = archived.len();
+
+        if count > 0 {
+            self.archive.extend(archived);
+            self.sort_archive();
+            self.save()?;
+        }
+
+        Ok(count)
+    }
+
+    // indices into `self.archive`, in ascending order
+    fn resolve_archive_selection(&self, selection: &Selection) -> Vec<usize> {
+        match selection {
+            Selection::Current => match self.view {
+                View::Archive(_) => self
+                    .table_state
+                    .selected()
+                    .filter(|&i| i < self.archive.len())
+                    .into_iter()
+                    .collect(),
+                _ => vec![],
             },
-            KeyCode::Down => self.next(),
-            KeyCode::Up => self.previous(),
-            _ => {}
+            Selection::Ids(_) => (0..self.archive.len())
+                .filter(|&i| selection.contains(self.archive[i].id))
+                .collect(),
         }
     }
 
-    pub fn load_tasks(&mut self) -> io::Result<()> {
-        // check if a .hourglass file exist
-        // if it does, load the content
-        // otherwise, create an empty .hourglass file
+    // restored tasks are reopened, otherwise the next start would archive them again
+    fn restore(&mut self, selection: &Selection) -> Result<String, String> {
+        let indices = self.resolve_archive_selection(selection);
+
+        if indices.is_empty() {
+            return Err(String::from("no matching archived task"));
+        }
 
-        let current_dir = env::current_dir()?;
+        self.checkpoint();
 
-        let paths = fs::read_dir(current_dir).unwrap();
-        let mut file_exists = false;
+        let position = self
+            .tasks
+            .iter()
+            .map(|task| task.position + 1)
+            .max()
+            .unwrap_or(0);
 
-        for path in paths {
-            let file_path = path.unwrap().path();
+        // remove from the back so the remaining indices stay valid
+        let mut restored: Vec<Task> = indices
+            .iter()
+            .rev()
+            .map(|&i| self.archive.remove(i))
+            .collect();
+        restored.reverse();
 
-            if let Some(os_extension) = file_path.extension() {
-                if let Some(extension) = os_extension.to_str() {
-                    if extension == HOURGLASS_EXTENSION {
-                        file_exists = true;
+        for (offset, mut task) in restored.into_iter().enumerate() {
+            task.set_completed(false);
+            task.position = position + offset as i32;
 
-                        let content =
-                            fs::read_to_string(file_path).expect("Unable to read .hourglass file");
+            self.tasks.push(task);
+        }
 
-                        let datas: Vec<Task> = serde_json::from_str(&content)?;
+        self.clamp_selection();
+        self.save()?;
 
-                        self.tasks = datas;
-                    }
-                }
+        Ok(format!("restored {} task(s)", indices.len()))
+    }
+
+    fn sort_archive(&mut self) {
+        self.archive
+            .sort_by_key(|task| cmp::Reverse(task.completed_at.unwrap_or(task.modified_at)));
+    }
+
+    // marks are consumed by the command that used them, like a visual selection in vim
+    fn clear_marks_used_by(&mut self, selection: &Selection) {
+        if let Selection::Current = selection {
+            self.clear_marks();
+        }
+    }
+
+    fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.mark_anchor = None;
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(id) = self.selected_task_index().map(|i| self.tasks[i].id) {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
             }
+
+            self.mark_anchor = Some(id);
         }
+    }
+
+    // marks every row between the anchor and the cursor, both included
+    fn mark_range(&mut self) {
+        let visible = self.visible_tasks();
+
+        let row = match self
+            .table_state
+            .selected()
+            .filter(|&row| row < visible.len())
+        {
+            Some(row) => row,
+            None => return,
+        };
+
+        let anchor = self
+            .mark_anchor
+            .and_then(|id| visible.iter().position(|&i| self.tasks[i].id == id))
+            .unwrap_or(row);
 
-        if !file_exists {
-            fs::write(HOURGLASS_FILE_STORAGE_NAME, "")?;
+        for &i in &visible[cmp::min(anchor, row)..=cmp::max(anchor, row)] {
+            self.marked.insert(self.tasks[i].id);
         }
 
-        Ok(())
+        self.mark_anchor = Some(self.tasks[visible[row]].id);
     }
 
-    fn save_tasks(&self) {
-        let serialized = serde_json::to_string(&self.tasks).unwrap();
+    fn mark_all(&mut self) {
+        let ids: Vec<i32> = self
+            .visible_tasks()
+            .into_iter()
+            .map(|i| self.tasks[i].id)
+            .collect();
 
-        fs::write(HOURGLASS_FILE_STORAGE_NAME, serialized).expect("Unable to write to file");
+        if ids.iter().all(|id| self.marked.contains(id)) {
+            for id in ids.iter() {
+                self.marked.remove(id);
+            }
+        } else {
+            self.marked.extend(ids);
+        }
     }
+
+    // swaps the selected task with the one shown above (negative offset) or below it
+    fn move_task(&mut self, offset: isize) {
+        if let Some(sort_key) = self.sort_key {
+            self.message = Some(Message::Error(format!(
+                "sorted by {}, use `:sort manual` to reorder",
+                sort_key.as_str()
+            )));
+
+            return;
+        }
+
+        let visible = self.visible_tasks();
+
+        let row = match self
+            .table_state
+            .selected()
+            .filter(|&row| row < visible.len())
+        {
+            Some(row) => row,
+            None => return,
+        };
+
+        let target = match row
+            .checked_add_signed(offset)
+            .filter(|&target| target < visible.len())
+        {
+            Some(target) => target,
+            None => return,
+        };
+
+        let (a, b) = (visible[row], visible[target]);
+
+        // the stores have separate orders, the repo tasks always come first
+        if self.tasks[a].location != self.tasks[b].location {
+            return;
+        }
+
+        self.checkpoint();
+
+        let position = self.tasks[a].position;
+
+        self.tasks[a].position = self.tasks[b].position;
+        self.tasks[b].position = position;
+        self.table_state.select(Some(target));
+
+        if let Err(err) = self.save() {
+            self.message = Some(Message::Error(err));
+        }
+    }
+
+    // files from before manual ordering have every position at 0, keep their order
+    fn normalize_positions(&mut self) {
+        let positions: BTreeSet<i32> = self.tasks.iter().map(|task| task.position).collect();
+
+        if positions.len() == self.tasks.len() {
+            return;
+        }
+
+        let mut indices: Vec<usize> = (0..self.tasks.len()).collect();
+        indices.sort_by_key(|&i| self.tasks[i].position);
+
+        for (position, i) in indices.into_iter().enumerate() {
+            self.tasks[i].position = position as i32;
+        }
+    }
+
+    fn start_modify(&mut self) {
+        self.set_command(":modify ");
+        self.set_action(Action::Command);
+    }
+
+    fn update_task(&mut self) {
+        if let Some(i) = self.selected_task_index() {
+            self.checkpoint();
+
+            if let Some(task) = self.tasks.get_mut(i) {
+                let input = self.command_input.as_str().to_string();
+
+                self.history.push(&input);
+                task.apply_input(parse_input(&input));
+
+                if let Err(err) = self.save() {
+                    self.message = Some(Message::Error(err));
+                }
+            }
+        }
+        self.clear_command();
+    }
+
+    // suspends the TUI, opens $EDITOR on the notes of the selected task and reads them back
+    fn edit_notes<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+    ) -> io::Result<()> {
+        let (id, notes) = match self.selected_task_index().and_then(|i| self.tasks.get(i)) {
+            Some(task) => (task.id, task.notes.clone()),
+            None => return Ok(()),
+        };
+
+        // a random name, created only if it doesn't exist yet, removed when dropped
+        let mut file = tempfile::Builder::new()
+            .prefix("hourglass-task-")
+            .suffix(".md")
+            .tempfile()?;
+        file.write_all(notes.as_bytes())?;
+        file.flush()?;
+
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| String::from("vi"));
+
+        // $EDITOR may contain arguments, e.g. "code --wait"
+        let mut editor_args = editor.split_whitespace();
+        let program = editor_args.next().unwrap_or("vi");
+
+        Hourglass::pause_tui()?;
+
+        let status = process::Command::new(program)
+            .args(editor_args)
+            .arg(file.path())
+            .status();
+
+        Hourglass::start_tui()?;
+        terminal.clear()?;
+
+        let edited = match status {
+            Ok(status) if status.success() => fs::read_to_string(file.path()).ok(),
+            _ => None,
+        };
+
+        drop(file);
+
+        if let Some(edited) = edited {
+            let edited = edited.trim_end().to_string();
+
+            if let Some(i) = self.tasks.iter().position(|task| task.id == id) {
+                if self.tasks[i].notes != edited {
+                    self.checkpoint();
+
+                    let task = &mut self.tasks[i];
+                    task.notes = edited;
+                    task.modified_at = Utc::now();
+
+                    if let Err(err) = self.save() {
+                        self.message = Some(Message::Error(err));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // pre-fills the command bar with the selected task so it can be edited instead of retyped
+    fn start_update(&mut self) {
+        if let Some(task) = self.selected_task_index().and_then(|i| self.tasks.get(i)) {
+            let input = task.to_input();
+
+            self.command_input
+                .update(&input, input.len(), &mut self.changes);
+            self.view = View::Task(Action::Update);
+        }
+    }
+
+    fn clear_command(&mut self) {
+        self.command_input.update("", 0, &mut self.changes);
+        self.history.reset();
+        self.completion = None;
+    }
+
+    fn set_command(&mut self, text: &str) {
+        self.command_input
+            .update(text, text.len(), &mut self.changes);
+    }
+
+    fn history_previous(&mut self) {
+        let current = self.command_input.as_str().to_string();
+
+        if let Some(entry) = self.history.previous(&current).map(String::from) {
+            self.set_command(&entry);
+        }
+    }
+
+    fn history_next(&mut self) {
+        if let Some(entry) = self.history.next().map(String::from) {
+            self.set_command(&entry);
+        }
+    }
+
+    fn open_completion(&mut self) {
+        let completion = complete(
+            self.command_input.as_str(),
+            self.command_input.pos(),
+            &self.tasks,
+            &self.issues,
+            &self.tabs,
+            &self.list_names(),
+        );
+
+        match completion {
+            // no need for a popup when there is nothing to choose from
+            Some(completion) if completion.candidates.len() == 1 => {
+                self.completion = Some(completion);
+                self.accept_completion();
+            }
+            completion => self.completion = completion,
+        }
+    }
+
+    fn accept_completion(&mut self) {
+        if let Some(completion) = self.completion.take() {
+            let candidate = completion.selected();
+
+            // attribute names like `due:` are followed by their value, not by a space
+            let text = if candidate.replacement.ends_with(':') {
+                candidate.replacement.clone()
+            } else {
+                format!("{} ", candidate.replacement)
+            };
+
+            self.command_input.replace(
+                completion.start..self.command_input.pos(),
+                &text,
+                &mut self.changes,
+            );
+        }
+    }
+
+    // returns whether the key was used by the completion popup
+    fn handle_key_for_completion(&mut self, key_code: KeyCode) -> bool {
+        if let Some(completion) = self.completion.as_mut() {
+            match key_code {
+                KeyCode::Tab | KeyCode::Down => completion.next(),
+                KeyCode::BackTab | KeyCode::Up => completion.previous(),
+                KeyCode::Enter => self.accept_completion(),
+                KeyCode::Esc => self.completion = None,
+                _ => {
+                    // typing goes on as usual, the popup is just closed
+                    self.completion = None;
+
+                    return false;
+                }
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    fn handle_input(&mut self, key_event: KeyEvent) {
+        self.message = None;
+
+        // we handle input differently based on the current view
+        let context = match self.key_context() {
+            Some(context) => context,
+            None => return self.update_command_input(key_event),
+        };
+
+        if self.show_help {
+            return self.handle_key_for_help(context, key_event);
+        }
+
+        if let KeyResult::Action(action) = self.keymap.handle(context, key_event) {
+            self.perform(action);
+        }
+    }
+
+    /// The bindings in use right now, `None` while typing in the command bar
+    fn key_context(&self) -> Option<KeyContext> {
+        match &self.view {
+            View::Task(Action::View) => Some(KeyContext::Tasks),
+            View::Issues(Action::View) => Some(KeyContext::Issues),
+            View::Archive(Action::View) => Some(KeyContext::Archive),
+            _ => None,
+        }
+    }
+
+    // the help popup goes through the keymap too, so it scrolls with the same keys as the rest
+    fn handle_key_for_help(&mut self, context: KeyContext, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Esc {
+            self.show_help = false;
+
+            return;
+        }
+
+        if let KeyResult::Action(action) = self.keymap.handle(context, key_event) {
+            match action {
+                KeyAction::Help | KeyAction::Quit => self.show_help = false,
+                KeyAction::Next => self.help_scroll = self.help_scroll.saturating_add(1),
+                KeyAction::Previous => self.help_scroll = self.help_scroll.saturating_sub(1),
+                KeyAction::PageDown => self.help_scroll = self.help_scroll.saturating_add(10),
+                KeyAction::PageUp => self.help_scroll = self.help_scroll.saturating_sub(10),
+                KeyAction::First => self.help_scroll = 0,
+                KeyAction::Last => self.help_scroll = u16::MAX,
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_paste(&mut self, text: &str) {
+        // pasting only makes sense while typing in the command input
+        let is_editing = match &self.view {
+            View::Task(action) | View::Issues(action) | View::Archive(action) => {
+                !matches!(action, Action::View)
+            }
+        };
+
+        if is_editing {
+            input::paste(&mut self.command_input, text, &mut self.changes);
+        }
+    }
+
+    fn handle_resize(&mut self, width: u16) {
+        let layout = LayoutMode::for_width(width);
+
+        // a details popup that was open on a narrow terminal would otherwise stay focused
+        // when it becomes a regular pane, and the other way around
+        if layout != self.layout {
+            self.focus = Focus::Table;
+        }
+
+        self.layout = layout;
+
+        // stale until the next draw
+        self.areas = Areas::default();
+    }
+
+    fn handle_mouse(&mut self, mouse_event: MouseEvent) {
+        let (column, row) = (mouse_event.column, mouse_event.row);
+
+        if self.show_help {
+            match mouse_event.kind {
+                MouseEventKind::ScrollDown => self.help_scroll = self.help_scroll.saturating_add(1),
+                MouseEventKind::ScrollUp => self.help_scroll = self.help_scroll.saturating_sub(1),
+                MouseEventKind::Down(_) => self.show_help = false,
+                _ => {}
+            }
+
+            return;
+        }
+
+        // the mouse is ignored while typing, a click could otherwise change the task being updated
+        if self.key_context().is_none() {
+            return;
+        }
+
+        let in_table = contains(self.areas.table_rows, column, row);
+        let in_details = contains(self.areas.details, column, row);
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(index) = self
+                    .areas
+                    .tabs
+                    .iter()
+                    .position(|area| contains(*area, column, row))
+                {
+                    if index != self.tab_index {
+                        self.tab_index = index;
+                        self.update_view();
+                    }
+                } else if in_table {
+                    self.click_row(row);
+                } else if in_details {
+                    self.focus = Focus::Details;
+                }
+            }
+            MouseEventKind::ScrollDown if in_details => {
+                self.scroll_details_down(WHEEL_SCROLL_LINES)
+            }
+            MouseEventKind::ScrollUp if in_details => self.scroll_details_up(WHEEL_SCROLL_LINES),
+            // unlike j/k the wheel stops at both ends of the table
+            MouseEventKind::ScrollDown if in_table => {
+                if let Some(i) = self.table_state.selected() {
+                    if i + 1 < self.row_count() {
+                        self.select_row(i + 1);
+                    }
+                }
+            }
+            MouseEventKind::ScrollUp if in_table => {
+                if let Some(i) = self.table_state.selected() {
+                    if i > 0 {
+                        self.select_row(i - 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn click_row(&mut self, row: u16) {
+        let index = self.table_state.offset() + (row - self.areas.table_rows.y) as usize;
+
+        if index >= self.row_count() {
+            return;
+        }
+
+        self.focus = Focus::Table;
+
+        let is_double_click = match self.last_click {
+            Some((at, last_row)) => last_row == row && at.elapsed() <= DOUBLE_CLICK_DELAY,
+            None => false,
+        };
+
+        if is_double_click {
+            self.last_click = None;
+
+            if let View::Task(_) = self.view {
+                self.start_update();
+            }
+
+            return;
+        }
+
+        self.last_click = Some((Instant::now(), row));
+
+        if self.table_state.selected() != Some(index) {
+            self.select_row(index);
+        }
+    }
+
+    // actions that work the same way in every view
+    fn perform(&mut self, action: KeyAction) {
+        let page = cmp::max(self.details_height, 1);
+        // while the details pane is focused the navigation keys scroll it
+        let details = self.focus == Focus::Details;
+
+        match action {
+            KeyAction::Quit => self.execute(Command::Quit),
+            KeyAction::Next if details => self.scroll_details_down(1),
+            KeyAction::Next => self.next(),
+            KeyAction::Previous if details => self.scroll_details_up(1),
+            KeyAction::Previous => self.previous(),
+            KeyAction::First if details => self.details_scroll = 0,
+            KeyAction::First => self.select_row(0),
+            KeyAction::Last if details => self.scroll_details_down(self.details_content_height),
+            KeyAction::Last => self.select_row(self.row_count().saturating_sub(1)),
+            KeyAction::PageDown => self.scroll_details_down(page),
+            KeyAction::PageUp => self.scroll_details_up(page),
+            KeyAction::NextTab => self.next_tab(),
+            KeyAction::PreviousTab => self.previous_tab(),
+            KeyAction::ToggleFocus => self.toggle_focus(),
+            KeyAction::GrowTable => self.grow_table(),
+            KeyAction::ShrinkTable => self.shrink_table(),
+            KeyAction::Command => self.start_command(),
+            KeyAction::Help => {
+                self.show_help = true;
+                self.help_scroll = 0;
+            }
+            _ => match self.view {
+                View::Task(_) => self.handle_key_for_task_view(action),
+                View::Archive(_) => self.handle_key_for_archive_view(action),
+                View::Issues(_) => {}
+            },
+        }
+    }
+
+    fn update_command_input(&mut self, key_event: KeyEvent) {
+        if self.handle_key_for_completion(key_event.code) {
+            return;
+        }
+
+        let key = COMMAND_LINE_KEYS
+            .iter()
+            .find(|(code, ..)| *code == key_event.code)
+            .map(|(.., key)| *key);
+
+        match key {
+            Some(CommandLineKey::Complete) => self.open_completion(),
+            Some(CommandLineKey::HistoryPrevious) => self.history_previous(),
+            Some(CommandLineKey::HistoryNext) => self.history_next(),
+            Some(CommandLineKey::Submit) => match &self.view {
+                View::Task(action) | View::Issues(action) | View::Archive(action) => match action {
+                    Action::Add => {
+                        let input = self.command_input.as_str().to_string();
+
+                        self.history.push(&input);
+                        self.clear_command();
+                        self.set_action(Action::View);
+                        self.execute(Command::Add(input));
+                    }
+                    Action::Update => {
+                        self.update_task();
+
+                        self.set_action(Action::View);
+                    }
+                    Action::Command => self.run_command_input(),
+                    _ => {}
+                },
+            },
+            Some(CommandLineKey::Cancel) => {
+                self.clear_command();
+                self.set_action(Action::View);
+            }
+            None => {
+                input::edit_line(&mut self.command_input, key_event, &mut self.changes);
+
+                // backspacing over the `:` leaves command mode, like in vim
+                if self.is_action(Action::Command) && self.command_input.is_empty() {
+                    self.set_action(Action::View);
+                }
+            }
+        }
+    }
+
+    fn run_command_input(&mut self) {
+        let input = self.command_input.as_str().to_string();
+
+        match command::parse(&input) {
+            Ok(command) => {
+                self.history.push(&input);
+                self.clear_command();
+                self.set_action(Action::View);
+                self.execute(command);
+            }
+            // stay in command mode so the command can be fixed
+            Err(err) => self.message = Some(Message::Error(err.to_string())),
+        }
+    }
+
+    fn start_command(&mut self) {
+        self.set_command(":");
+        self.set_action(Action::Command);
+    }
+
+    fn set_action(&mut self, action: Action) {
+        self.view = match self.view {
+            View::Task(_) => View::Task(action),
+            View::Issues(_) => View::Issues(action),
+            View::Archive(_) => View::Archive(action),
+        };
+    }
+
+    fn is_action(&self, action: Action) -> bool {
+        match &self.view {
+            View::Task(current) | View::Issues(current) | View::Archive(current) => {
+                *current == action
+            }
+        }
+    }
+
+    fn handle_key_for_task_view(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Add => self.view = View::Task(Action::Add),
+            KeyAction::Update if !self.marked.is_empty() => self.start_modify(),
+            KeyAction::Update => self.start_update(),
+            KeyAction::Toggle => self.execute(Command::Toggle(Selection::Current)),
+            KeyAction::Delete => self.execute(Command::Delete(Selection::Current)),
+            KeyAction::EditNotes => self.should_edit_notes = true,
+            KeyAction::Mark => self.toggle_mark(),
+            KeyAction::MarkRange => self.mark_range(),
+            KeyAction::MarkAll => self.mark_all(),
+            KeyAction::ClearMarks => self.clear_marks(),
+            KeyAction::Modify => self.start_modify(),
+            KeyAction::MoveToList => {
+                self.set_command(":move ");
+                self.set_action(Action::Command);
+            }
+            KeyAction::NewList => {
+                self.set_command(":list ");
+                self.set_action(Action::Command);
+            }
+            KeyAction::Undo => self.execute(Command::Undo),
+            KeyAction::MoveUp => self.move_task(-1),
+            KeyAction::MoveDown => self.move_task(1),
+            KeyAction::CycleScope => {
+                let mut scope = self.scope.next();
+
+                if scope == Scope::Repo && !self.has_repo() {
+                    scope = scope.next();
+                }
+
+                self.execute(Command::Scope(scope));
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_key_for_archive_view(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Restore => self.execute(Command::Restore(Selection::Current)),
+            KeyAction::Purge => self.execute(Command::Purge(Selection::Current)),
+            _ => {}
+        }
+    }
+
+    pub fn load_tasks(&mut self) -> io::Result<()> {
+        self.tasks.clear();
+        self.archive.clear();
+
+        // the ids of the global tasks stay the same, clashing ones in the repo get new ones
+        let mut ids = BTreeSet::new();
+
+        for located in self.stores.iter_mut() {
+            // another instance may be halfway through writing them
+            let _lock = storage::lock(&located.dir, &self.config).map_err(invalid_data)?;
+            let (tasks, archive) = located.store.load().map_err(invalid_data)?;
+
+            for (mut task, archived) in tasks
+                .into_iter()
+                .map(|task| (task, false))
+                .chain(archive.into_iter().map(|task| (task, true)))
+            {
+                task.location = located.location;
+
+                if task.id != 0 && !ids.insert(task.id) {
+                    task.stored_id = Some(task.id);
+                    task.id = 0;
+                }
+
+                match archived {
+                    true => self.archive.push(task),
+                    false => self.tasks.push(task),
+                }
+            }
+        }
+
+        self.normalize_positions();
+        self.sort_archive();
+
+        // both lists are checked before saving, a save writes them together
+        let tasks_assigned = assign_uuids(&mut self.tasks);
+
+        if assign_uuids(&mut self.archive) || tasks_assigned {
+            self.save().map_err(invalid_data)?;
+        }
+
+        // ids keep growing after the archived tasks so restoring one never clashes
+        self.next_id = self
+            .tasks
+            .iter()
+            .chain(self.archive.iter())
+            .map(|task| task.id + 1)
+            .max()
+            .unwrap_or(1);
+
+        self.assign_ids();
+        self.update_tabs();
+
+        for i in 0..self.stores.len() {
+            self.update_synced(i);
+        }
+
+        if self.config.archive_after_days > 0 {
+            let min_age = chrono::Duration::days(self.config.archive_after_days as i64);
+
+            self.archive_completed(Some(min_age))
+                .map_err(invalid_data)?;
+        }
+
+        Ok(())
+    }
+
+    // tasks read from the lines format are numbered in list order, then the archived ones
+    fn assign_ids(&mut self) {
+        let mut indices: Vec<usize> = (0..self.tasks.len()).collect();
+        indices.sort_by_key(|&i| self.tasks[i].position);
+
+        for i in indices {
+            if self.tasks[i].id == 0 {
+                self.tasks[i].id = self.next_id;
+                self.next_id += 1;
+            }
+        }
+
+        for task in self.archive.iter_mut().filter(|task| task.id == 0) {
+            task.id = self.next_id;
+            self.next_id += 1;
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        for i in 0..self.stores.len() {
+            self.save_store(i)
+                .map_err(|err| format!("unable to save tasks: {}", err))?;
+        }
+
+        Ok(())
+    }
+
+    // merges what was written to the store since it was last loaded or saved, then saves it.
+    // Both happen under the lock so another instance can't write in between
+    fn save_store(&mut self, i: usize) -> Result<(), String> {
+        let _lock = storage::lock(&self.stores[i].dir, &self.config)?;
+
+        if let Some(conflicts) = self.merge_external(i)? {
+            let location = self.stores[i].location.as_str();
+
+            self.external_changes = Some(match conflicts.first() {
+                None => Message::Info(format!(
+                    "reloaded the {} tasks changed outside hourglass",
+                    location
+                )),
+                Some(conflict) => Message::Error(format!(
+                    "{} conflict(s) with changes made outside hourglass, {}",
+                    conflicts.len(),
+                    conflict
+                )),
+            });
+        }
+
+        let located = &mut self.stores[i];
+        let (tasks, archive) = stored_tasks_in(&self.tasks, &self.archive, located.location);
+
+        located.store.save(
+            &tasks.iter().collect::<Vec<_>>(),
+            &archive.iter().collect::<Vec<_>>(),
+        )?;
+        self.update_synced(i);
+
+        Ok(())
+    }
+
+    // polled on every tick, a store that changed is saved with the changes merged in
+    fn reload_changed_stores(&mut self) {
+        for i in 0..self.stores.len() {
+            // checked again under the lock
+            if !self.stores[i].store.reload_needed().unwrap_or(false) {
+                continue;
+            }
+
+            if let Err(err) = self.save_store(i) {
+                self.external_changes = Some(Message::Error(err));
+            }
+        }
+
+        if let Some(message) = self.external_changes.take() {
+            self.message = Some(message);
+        }
+    }
+
+    // the conflicts of the merge, `None` when nothing else wrote to the store. The side that
+    // modified a task last wins a conflict, see `storage::merge`
+    fn merge_external(&mut self, i: usize) -> Result<Option<Vec<String>>, String> {
+        let located = &mut self.stores[i];
+
+        if !located.store.changed()? {
+            return Ok(None);
+        }
+
+        let location = located.location;
+        let (mut their_tasks, mut their_archive) = located.store.load()?;
+        let (our_tasks, our_archive) = tasks_in(&self.tasks, &self.archive, location);
+        let our_tasks: Vec<Task> = our_tasks.into_iter().cloned().collect();
+        let our_archive: Vec<Task> = our_archive.into_iter().cloned().collect();
+
+        // the tasks keep the ids they have here when the store has none, like the lines
+        // format, or the same one as before
+        for task in their_tasks.iter_mut().chain(their_archive.iter_mut()) {
+            if let Some(ours) = our_tasks
+                .iter()
+                .chain(our_archive.iter())
+                .find(|ours| ours.uuid == task.uuid)
+                .filter(|ours| task.id == 0 || Some(task.id) == ours.stored_id)
+            {
+                task.id = ours.id;
+                task.stored_id = ours.stored_id;
+            }
+        }
+
+        let merged = storage::merge_store(
+            (&located.synced.0, &located.synced.1),
+            (&our_tasks, &our_archive),
+            (&their_tasks, &their_archive),
+        )?;
+
+        located.synced = (their_tasks, their_archive);
+
+        // the merge goes through JSON, which leaves out the stored ids
+        let in_location = |mut task: Task| {
+            task.location = location;
+            task.stored_id = our_tasks
+                .iter()
+                .chain(our_archive.iter())
+                .find(|ours| ours.uuid == task.uuid && ours.id == task.id)
+                .and_then(|ours| ours.stored_id);
+            task
+        };
+
+        self.tasks.retain(|task| task.location != location);
+        self.archive.retain(|task| task.location != location);
+        self.tasks.extend(merged.tasks.into_iter().map(in_location));
+        self.archive
+            .extend(merged.archive.into_iter().map(in_location));
+
+        self.normalize_positions();
+        self.sort_archive();
+        self.renumber_clashing_ids();
+        self.update_tabs();
+        self.clamp_selection();
+
+        // undoing would bring back what was there before the merge
+        self.undo_stack.clear();
+
+        Ok(Some(merged.conflicts))
+    }
+
+    // what the store holds after a load or a save
+    fn update_synced(&mut self, i: usize) {
+        let located = &mut self.stores[i];
+        let (tasks, archive) = tasks_in(&self.tasks, &self.archive, located.location);
+
+        located.synced = (
+            tasks.into_iter().cloned().collect(),
+            archive.into_iter().cloned().collect(),
+        );
+    }
+
+    // tasks added by another instance can have the id of one shown here, which keeps it
+    fn renumber_clashing_ids(&mut self) {
+        let mut ids = BTreeSet::new();
+
+        for task in self.tasks.iter_mut().chain(self.archive.iter_mut()) {
+            if task.id != 0 && !ids.insert(task.id) {
+                task.stored_id.get_or_insert(task.id);
+                task.id = 0;
+            }
+        }
+
+        self.next_id = cmp::max(self.next_id, ids.last().map_or(1, |id| id + 1));
+        self.assign_ids();
+    }
+
+    // the store the tasks tab shows, the repo one when it shows both
+    fn new_task_location(&self) -> Location {
+        match self.scope {
+            Scope::Global => Location::Global,
+            _ if self.has_repo() => Location::Repo,
+            _ => Location::Global,
+        }
+    }
+
+    fn has_repo(&self) -> bool {
+        self.stores
+            .iter()
+            .any(|located| located.location == Location::Repo)
+    }
+
+    // the named lists of every store, whatever the scope
+    fn lists(&self) -> BTreeSet<String> {
+        self.stores
+            .iter()
+            .flat_map(|located| located.store.lists())
+            .collect()
+    }
+
+    // what `:move` and `:list` take, the default list first
+    fn list_names(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_LIST.to_string()];
+        names.extend(self.lists());
+
+        names
+    }
+
+    fn update_tabs(&mut self) {
+        let current = self.tabs.get(self.tab_index).cloned();
+        let lists = self.lists();
+        let mut tabs = vec![];
+
+        for tab in self.config.tabs.iter() {
+            tabs.push(tab.clone());
+
+            if tab == "tasks" {
+                tabs.extend(lists.iter().cloned());
+            }
+        }
+
+        self.tabs = tabs;
+        self.tab_index = current
+            .and_then(|current| self.tabs.iter().position(|tab| *tab == current))
+            .unwrap_or(0);
+    }
+
+    fn resolve_list(&self, name: &str) -> Result<Option<String>, String> {
+        if name == DEFAULT_LIST {
+            Ok(None)
+        } else if self.lists().contains(name) {
+            Ok(Some(name.to_string()))
+        } else {
+            Err(format!("no list named `{}`", name))
+        }
+    }
+
+    /// The list `export` and `import` work on, `tasks` is the default one
+    pub fn select_list(&mut self, name: &str) -> Result<(), String> {
+        self.list = self.resolve_list(name)?;
+
+        Ok(())
+    }
+
+    fn open_list(&mut self, name: &str) -> Result<String, String> {
+        let created = match self.resolve_list(name) {
+            Ok(_) => false,
+            Err(_) => {
+                check_list_name(name)?;
+
+                let location = self.new_task_location();
+                let located = self
+                    .stores
+                    .iter_mut()
+                    .find(|located| located.location == location)
+                    .ok_or_else(|| {
+                        format!("no {} store to create the list in", location.as_str())
+                    })?;
+
+                located.store.create_list(name)?;
+                self.update_tabs();
+
+                true
+            }
+        };
+
+        self.switch_to_tab(name);
+
+        match created {
+            true => Ok(format!("created list {}", name)),
+            false => Ok(String::new()),
+        }
+    }
+
+    fn move_to_list(&mut self, selection: &Selection, name: &str) -> Result<String, String> {
+        let list = self
+            .resolve_list(name)
+            .map_err(|err| format!("{}, `:list {}` creates it", err, name))?;
+        let indices = self.resolve_selection(selection);
+
+        if indices.is_empty() {
+            return Err(String::from("no matching task"));
+        }
+
+        self.checkpoint();
+
+        // at the bottom of the other list
+        let mut position = self
+            .tasks
+            .iter()
+            .map(|task| task.position + 1)
+            .max()
+            .unwrap_or(0);
+
+        for &i in indices.iter() {
+            let task = &mut self.tasks[i];
+
+            if task.list != list {
+                task.list = list.clone();
+                task.position = position;
+                task.modified_at = Utc::now();
+                position += 1;
+            }
+        }
+
+        self.clamp_selection();
+        self.clear_marks_used_by(selection);
+        self.save()?;
+
+        Ok(format!("moved {} task(s) to {}", indices.len(), name))
+    }
+
+    fn set_scope(&mut self, scope: Scope) -> Result<String, String> {
+        if scope == Scope::Repo && !self.has_repo() {
+            return Err(String::from("not in a git repository"));
+        }
+
+        self.scope = scope;
+        self.clamp_selection();
+
+        Ok(format!("showing {} tasks", scope.as_str()))
+    }
+}
+
+// the part of the task list and of the archive kept in one store
+fn tasks_in<'a>(
+    tasks: &'a [Task],
+    archive: &'a [Task],
+    location: Location,
+) -> (Vec<&'a Task>, Vec<&'a Task>) {
+    let in_location = |task: &&Task| task.location == location;
+
+    (
+        tasks.iter().filter(in_location).collect(),
+        archive.iter().filter(in_location).collect(),
+    )
+}
+
+// like `tasks_in`, with the ids the store gave them
+fn stored_tasks_in(tasks: &[Task], archive: &[Task], location: Location) -> (Vec<Task>, Vec<Task>) {
+    let stored = |tasks: Vec<&Task>| {
+        tasks
+            .into_iter()
+            .map(|task| Task {
+                id: task.stored_id.unwrap_or(task.id),
+                ..task.clone()
+            })
+            .collect()
+    };
+
+    let (tasks, archive) = tasks_in(tasks, archive, location);
+
+    (stored(tasks), stored(archive))
+}
+
+// a list is a file name in the JSON store and a tab title
+fn check_list_name(name: &str) -> Result<(), String> {
+    if KNOWN_TABS.contains(&name) {
+        Err(format!("`{}` is already a tab", name))
+    } else if name.starts_with('.') || name.contains(['/', '\\']) {
+        Err(format!("invalid list name `{}`", name))
+    } else {
+        Ok(())
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// files from before uuids existed, saved right away so the uuids stay the same
+fn assign_uuids(tasks: &mut [Task]) -> bool {
+    let mut assigned = false;
+
+    for task in tasks.iter_mut().filter(|task| task.uuid.is_nil()) {
+        task.uuid = uuid::Uuid::new_v4();
+        assigned = true;
+    }
+
+    assigned
+}
+
+fn contains(rect: Rect, column: u16, row: u16) -> bool {
+    column >= rect.x && column < rect.x + rect.width && row >= rect.y && row < rect.y + rect.height
 }
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{deserialize, serialize, StorageFormat, Store};
+use crate::app::command::Filter;
+use crate::app::config::Config;
+use crate::app::task::Task;
+use crate::app::HOURGLASS_EXTENSION;
+
+/// The task list and the archive in JSON files, see `StorageFormat`. The default list is
+/// `storage_file`, every other `.hourglass` file next to it is a named list
+pub struct JsonStore {
+    dir: PathBuf,
+    storage_file: PathBuf,
+    archive_file: PathBuf,
+    format: StorageFormat,
+    // the named lists, an empty one keeps its file
+    lists: BTreeSet<String>,
+    // what the files hold, a file is only written again when its content changes
+    saved: BTreeMap<PathBuf, String>,
+    // when the files were last modified and their size, after the last load or save
+    stamps: Vec<Stamp>,
+    // the stamps when the last load failed, until a load or a save succeeds
+    failed: Option<Vec<Stamp>>,
+}
+
+type Stamp = (PathBuf, Option<(SystemTime, u64)>);
+
+// one list as read from its file
+struct ListFile {
+    list: Option<String>,
+    path: PathBuf,
+    content: String,
+    tasks: Vec<Task>,
+}
+
+impl JsonStore {
+    pub fn new(config: &Config, dir: &Path) -> Self {
+        JsonStore {
+            dir: dir.to_path_buf(),
+            storage_file: dir.join(&config.storage_file),
+            archive_file: dir.join(&config.archive_file),
+            format: config.storage_format,
+            lists: BTreeSet::new(),
+            saved: BTreeMap::new(),
+            stamps: vec![],
+            failed: None,
+        }
+    }
+
+    fn list_file(&self, list: Option<&str>) -> PathBuf {
+        match list {
+            Some(name) => self.dir.join(format!("{}.{}", name, HOURGLASS_EXTENSION)),
+            None => self.storage_file.clone(),
+        }
+    }
+
+    // the default list first, then the named ones by name
+    fn list_files(&self) -> Vec<(Option<String>, PathBuf)> {
+        let mut named: Vec<(Option<String>, PathBuf)> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                *path != self.storage_file
+                    && path
+                        .extension()
+                        .is_some_and(|extension| extension == HOURGLASS_EXTENSION)
+            })
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?.to_string();
+
+                Some((Some(name), path))
+            })
+            .collect();
+
+        named.sort();
+        named.insert(0, (None, self.storage_file.clone()));
+
+        named
+    }
+
+    // the tasks of every list, each one knowing which list it is in
+    fn read_lists(&self) -> Result<Vec<ListFile>, String> {
+        let mut lists = vec![];
+
+        for (list, path) in self.list_files() {
+            if let Some((content, mut tasks)) = read_file(&path)? {
+                for task in tasks.iter_mut() {
+                    task.list = list.clone();
+                }
+
+                lists.push(ListFile {
+                    list,
+                    path,
+                    content,
+                    tasks,
+                });
+            }
+        }
+
+        Ok(lists)
+    }
+
+    // a list file added or removed changes them too
+    fn stamps(&self) -> Vec<Stamp> {
+        self.list_files()
+            .into_iter()
+            .map(|(_, path)| path)
+            .chain([self.archive_file.clone()])
+            .map(|path| {
+                let stamp = fs::metadata(&path)
+                    .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+                    .ok();
+
+                (path, stamp)
+            })
+            .collect()
+    }
+
+    fn write(&mut self, file: &Path, content: String) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(file, &content))
+            .map_err(|err| format!("unable to write to {}: {}", file.display(), err))?;
+
+        self.saved.insert(file.to_path_buf(), content);
+
+        Ok(())
+    }
+}
+
+impl Store for JsonStore {
+    fn load(&mut self) -> Result<(Vec<Task>, Vec<Task>), String> {
+        // taken before reading, a write made in the meantime is a change
+        let stamps = self.stamps();
+
+        // nothing is kept from a failed load, the last one that worked stays
+        let read = self
+            .read_lists()
+            .and_then(|files| Ok((files, read_file(&self.archive_file)?)));
+
+        let (files, archive) = match read {
+            Ok(read) => read,
+            Err(err) => {
+                self.failed = Some(stamps);
+                return Err(err);
+            }
+        };
+
+        let mut tasks = vec![];
+
+        self.lists.clear();
+        self.saved.clear();
+
+        // a missing file is created once there is a task to save, not in every directory
+        // hourglass starts in
+        for file in files {
+            self.lists.extend(file.list);
+            self.saved.insert(file.path, file.content);
+            tasks.extend(file.tasks);
+        }
+
+        let archive = match archive {
+            Some((content, archive)) => {
+                self.saved.insert(self.archive_file.clone(), content);
+                archive
+            }
+            None => vec![],
+        };
+
+        self.stamps = stamps;
+        self.failed = None;
+
+        Ok((tasks, archive))
+    }
+
+    fn save(&mut self, tasks: &[&Task], archive: &[&Task]) -> Result<(), String> {
+        self.lists
+            .extend(tasks.iter().filter_map(|task| task.list.clone()));
+
+        let lists = std::iter::once(None).chain(self.lists.iter().cloned().map(Some));
+        let mut files: Vec<(PathBuf, Vec<&Task>)> = lists
+            .map(|list| {
+                let in_list = tasks
+                    .iter()
+                    .copied()
+                    .filter(|task| task.list == list)
+                    .collect();
+
+                (self.list_file(list.as_deref()), in_list)
+            })
+            .collect();
+
+        files.push((self.archive_file.clone(), archive.to_vec()));
+
+        for (file, tasks) in files {
+            let serialized = serialize(&tasks, self.format);
+
+            let unchanged = match self.saved.get(&file) {
+                Some(saved) => *saved == serialized,
+                None => tasks.is_empty(),
+            };
+
+            if !unchanged {
+                self.write(&file, serialized)?;
+            }
+        }
+
+        self.stamps = self.stamps();
+        self.failed = None;
+
+        Ok(())
+    }
+
+    // the whole files are read, there is nothing to narrow the reading down with
+    fn query(&self, filter: &Filter) -> Result<Vec<Task>, String> {
+        let mut tasks: Vec<Task> = self
+            .read_lists()?
+            .into_iter()
+            .flat_map(|file| file.tasks)
+            .collect();
+
+        tasks.sort_by_key(|task| task.position);
+
+        // numbered like the task list does when the lines format leaves the ids out
+        for (i, task) in tasks.iter_mut().enumerate() {
+            if task.id == 0 {
+                task.id = i as i32 + 1;
+            }
+        }
+
+        tasks.retain(|task| filter.matches(task));
+
+        Ok(tasks)
+    }
+
+    fn lists(&self) -> Vec<String> {
+        self.lists.iter().cloned().collect()
+    }
+
+    fn create_list(&mut self, name: &str) -> Result<(), String> {
+        let file = self.list_file(Some(name));
+
+        if !file.exists() {
+            self.write(&file, serialize(&[], self.format))?;
+        }
+
+        self.lists.insert(name.to_string());
+        self.stamps = self.stamps();
+
+        Ok(())
+    }
+
+    // the files aren't read, a different modification time or size is enough
+    fn changed(&self) -> Result<bool, String> {
+        Ok(self.stamps() != self.stamps)
+    }
+
+    fn reload_needed(&self) -> Result<bool, String> {
+        match &self.failed {
+            Some(failed) => Ok(self.stamps() != *failed),
+            None => self.changed(),
+        }
+    }
+}
+
+// `None` when the file doesn't exist yet
+fn read_file(path: &Path) -> Result<Option<(String, Vec<Task>)>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("unable to read {}: {}", path.display(), err)),
+    };
+
+    let tasks = deserialize(&content).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    Ok(Some((content, tasks)))
+}
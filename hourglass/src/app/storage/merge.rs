@@ -0,0 +1,367 @@
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use uuid::Uuid;
+
+use crate::app::task::Task;
+
+// lists merged item by item, what either side added or removed is kept
+const SET_FIELDS: [&str; 2] = ["tags", "annotations"];
+// changes with every edit, the latest one is kept without reporting it
+const MODIFIED_AT: &str = "modified_at";
+
+pub struct Merged {
+    pub tasks: Vec<Task>,
+    // fields both sides changed, and whose version was kept
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way merge of task lists, task by task through their uuid and then field by field.
+/// When both sides changed the same field, the side that modified the task last wins
+pub fn merge(base: &[Task], ours: &[Task], theirs: &[Task]) -> Result<Merged, String> {
+    let base_tasks = by_uuid(base);
+    let our_tasks = by_uuid(ours);
+    let their_tasks = by_uuid(theirs);
+
+    let mut merged = Merged {
+        tasks: vec![],
+        conflicts: vec![],
+    };
+
+    // our order first, then the tasks only they have
+    let mut seen = BTreeSet::new();
+    let uuids: Vec<Uuid> = ours
+        .iter()
+        .chain(theirs.iter())
+        .map(|task| task.uuid)
+        .filter(|uuid| seen.insert(*uuid))
+        .collect();
+
+    for uuid in uuids {
+        let task = match (
+            base_tasks.get(&uuid),
+            our_tasks.get(&uuid),
+            their_tasks.get(&uuid),
+        ) {
+            (base, Some(ours), Some(theirs)) => Some(merge_task(
+                base.copied(),
+                ours,
+                theirs,
+                &mut merged.conflicts,
+            )?),
+            // added on one side
+            (None, Some(task), None) | (None, None, Some(task)) => Some((*task).clone()),
+            // deleted on the other side, unless it was changed on this one
+            (Some(base), Some(task), None) | (Some(base), None, Some(task)) => {
+                if fields(base) == fields(task) {
+                    None
+                } else {
+                    merged.conflicts.push(format!(
+                        "\"{}\" was deleted on one side and changed on the other, it was kept",
+                        task.description
+                    ));
+
+                    Some((*task).clone())
+                }
+            }
+            _ => None,
+        };
+
+        merged.tasks.extend(task);
+    }
+
+    renumber_duplicate_ids(&mut merged.tasks);
+
+    Ok(merged)
+}
+
+fn merge_task(
+    base: Option<&Task>,
+    ours: &Task,
+    theirs: &Task,
+    conflicts: &mut Vec<String>,
+) -> Result<Task, String> {
+    let base_fields = base.map(fields);
+    let our_fields = fields(ours);
+    let their_fields = fields(theirs);
+    let theirs_win = theirs.modified_at > ours.modified_at;
+
+    let keys: BTreeSet<&String> = our_fields.keys().chain(their_fields.keys()).collect();
+    let mut merged = Map::new();
+    let mut conflicting = vec![];
+
+    for key in keys {
+        let base = base_fields.as_ref().and_then(|fields| fields.get(key));
+        let ours = our_fields.get(key);
+        let theirs = their_fields.get(key);
+
+        let value = if ours == theirs || theirs == base {
+            ours.cloned()
+        } else if ours == base {
+            theirs.cloned()
+        } else if SET_FIELDS.contains(&key.as_str()) {
+            Some(merge_set(base, ours, theirs))
+        } else {
+            if key != MODIFIED_AT {
+                conflicting.push(key.as_str());
+            }
+
+            if theirs_win {
+                theirs.cloned()
+            } else {
+                ours.cloned()
+            }
+        };
+
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+
+    if !conflicting.is_empty() {
+        conflicts.push(format!(
+            "\"{}\": {} changed on both sides, {} version was kept",
+            ours.description,
+            conflicting.join(", "),
+            if theirs_win { "their" } else { "our" }
+        ));
+    }
+
+    serde_json::from_value(Value::Object(merged))
+        .map_err(|err| format!("unable to merge \"{}\": {}", ours.description, err))
+}
+
+// items removed on either side are dropped, items added on either side are kept
+fn merge_set(base: Option<&Value>, ours: Option<&Value>, theirs: Option<&Value>) -> Value {
+    let items =
+        |value: Option<&Value>| value.and_then(Value::as_array).cloned().unwrap_or_default();
+
+    let (base, ours, theirs) = (items(base), items(ours), items(theirs));
+
+    let mut merged: Vec<Value> = ours
+        .into_iter()
+        .filter(|item| !base.contains(item) || theirs.contains(item))
+        .collect();
+
+    for item in theirs {
+        if !base.contains(&item) && !merged.contains(&item) {
+            merged.push(item);
+        }
+    }
+
+    Value::Array(merged)
+}
+
+pub struct MergedStore {
+    pub tasks: Vec<Task>,
+    pub archive: Vec<Task>,
+    pub conflicts: Vec<String>,
+}
+
+/// `merge` of the task lists and the archives together, a task goes to the archive or back
+/// to the list when either side moved it there
+pub fn merge_store(
+    base: (&[Task], &[Task]),
+    ours: (&[Task], &[Task]),
+    theirs: (&[Task], &[Task]),
+) -> Result<MergedStore, String> {
+    let all = |(tasks, archive): (&[Task], &[Task])| -> Vec<Task> {
+        tasks.iter().chain(archive.iter()).cloned().collect()
+    };
+    let uuids = |tasks: &[Task]| -> BTreeSet<Uuid> { tasks.iter().map(|task| task.uuid).collect() };
+
+    let merged = merge(&all(base), &all(ours), &all(theirs))?;
+
+    let (base_archived, our_archived) = (uuids(base.1), uuids(ours.1));
+    let (their_tasks, their_archived) = (uuids(&all(theirs)), uuids(theirs.1));
+
+    let (archive, tasks) = merged.tasks.into_iter().partition(|task| {
+        let uuid = &task.uuid;
+
+        if their_tasks.contains(uuid)
+            && their_archived.contains(uuid) != base_archived.contains(uuid)
+        {
+            their_archived.contains(uuid)
+        } else {
+            our_archived.contains(uuid)
+        }
+    });
+
+    Ok(MergedStore {
+        tasks,
+        archive,
+        conflicts: merged.conflicts,
+    })
+}
+
+// both sides may have given the same id to different new tasks
+fn renumber_duplicate_ids(tasks: &mut [Task]) {
+    let mut next_id = tasks.iter().map(|task| task.id + 1).max().unwrap_or(1);
+    let mut ids = BTreeSet::new();
+
+    // tasks from the lines format have no id yet
+    for task in tasks.iter_mut().filter(|task| task.id != 0) {
+        if !ids.insert(task.id) {
+            task.id = next_id;
+            next_id += 1;
+        }
+    }
+}
+
+fn by_uuid(tasks: &[Task]) -> BTreeMap<Uuid, &Task> {
+    tasks.iter().map(|task| (task.uuid, task)).collect()
+}
+
+fn fields(task: &Task) -> Map<String, Value> {
+    match serde_json::to_value(task) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use std::slice;
+
+    use super::*;
+
+    fn task(id: i32, description: &str) -> Task {
+        let mut task = Task::new(id, Default::default());
+        task.description = description.to_string();
+
+        task
+    }
+
+    // the same task changed later
+    fn changed(task: &Task, change: impl FnOnce(&mut Task)) -> Task {
+        let mut task = task.clone();
+        change(&mut task);
+        task.modified_at += Duration::seconds(1);
+
+        task
+    }
+
+    fn descriptions(merged: &Merged) -> Vec<&str> {
+        merged
+            .tasks
+            .iter()
+            .map(|task| task.description.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_tasks_added_on_either_side() {
+        let a = task(1, "a");
+        let b = task(2, "b");
+        let c = task(3, "c");
+
+        let merged = merge(slice::from_ref(&a), &[a.clone(), b], &[a.clone(), c]).unwrap();
+
+        assert_eq!(descriptions(&merged), ["a", "b", "c"]);
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn drops_tasks_deleted_on_either_side() {
+        let a = task(1, "a");
+        let b = task(2, "b");
+
+        let merged = merge(&[a.clone(), b.clone()], &[b], &[a]).unwrap();
+
+        assert!(merged.tasks.is_empty());
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_tasks_deleted_on_one_side_and_changed_on_the_other() {
+        let a = task(1, "a");
+        let ours = changed(&a, |task| task.completed = true);
+
+        let merged = merge(&[a], &[ours], &[]).unwrap();
+
+        assert_eq!(descriptions(&merged), ["a"]);
+        assert!(merged.tasks[0].completed);
+        assert_eq!(merged.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn merges_different_fields() {
+        let a = task(1, "a");
+        let ours = changed(&a, |task| task.project = Some(String::from("home")));
+        let theirs = changed(&a, |task| task.description = String::from("b"));
+
+        let merged = merge(&[a], &[ours], &[theirs]).unwrap();
+
+        assert_eq!(descriptions(&merged), ["b"]);
+        assert_eq!(merged.tasks[0].project.as_deref(), Some("home"));
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn latest_change_wins_a_conflict() {
+        let a = task(1, "a");
+        let ours = changed(&a, |task| task.description = String::from("ours"));
+        let theirs = changed(&ours, |task| task.description = String::from("theirs"));
+
+        let merged = merge(&[a], &[ours], &[theirs]).unwrap();
+
+        assert_eq!(descriptions(&merged), ["theirs"]);
+        assert_eq!(merged.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn merges_set_fields_item_by_item() {
+        let mut a = task(1, "a");
+        a.tags = vec![String::from("x"), String::from("y")];
+
+        let ours = changed(&a, |task| {
+            task.tags.retain(|tag| tag != "x");
+            task.tags.push(String::from("ours"));
+        });
+        let theirs = changed(&a, |task| task.tags.push(String::from("theirs")));
+
+        let merged = merge(&[a], &[ours], &[theirs]).unwrap();
+
+        assert_eq!(merged.tasks[0].tags, ["y", "ours", "theirs"]);
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn renumbers_ids_given_on_both_sides() {
+        let a = task(1, "a");
+        let b = task(2, "b");
+        let c = task(2, "c");
+
+        let merged = merge(slice::from_ref(&a), &[a.clone(), b], &[a.clone(), c]).unwrap();
+
+        let ids: Vec<i32> = merged.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[test]
+    fn leaves_missing_ids_alone() {
+        let a = task(0, "a");
+        let b = task(0, "b");
+
+        let merged = merge(&[], &[a], &[b]).unwrap();
+
+        let ids: Vec<i32> = merged.tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [0, 0]);
+    }
+
+    #[test]
+    fn moves_tasks_archived_on_either_side() {
+        let a = task(1, "a");
+        let b = task(2, "b");
+
+        let merged = merge_store(
+            (&[a.clone(), b.clone()], &[]),
+            (slice::from_ref(&b), slice::from_ref(&a)),
+            (slice::from_ref(&a), slice::from_ref(&b)),
+        )
+        .unwrap();
+
+        assert!(merged.tasks.is_empty());
+        assert_eq!(merged.archive.len(), 2);
+    }
+}
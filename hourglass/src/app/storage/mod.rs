@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use crate::app::command::Filter;
+use crate::app::config::Config;
+use crate::app::task::Task;
+use crate::app::HOURGLASS_EXTENSION;
+use crate::util::data_dir;
+
+mod json;
+mod merge;
+mod sqlite;
+
+pub use json::JsonStore;
+pub use merge::{merge, merge_store};
+pub use sqlite::SqliteStore;
+
+// held by whoever reads or writes the files of a store, another instance waits for it
+const LOCK_FILE: &str = ".hourglass.lock";
+
+/// Where the task list and the archive are kept between runs
+pub trait Store {
+    /// The task list and the archive
+    fn load(&mut self) -> Result<(Vec<Task>, Vec<Task>), String>;
+    /// Called after every change, only what changed since the last save needs writing
+    fn save(&mut self, tasks: &[&Task], archive: &[&Task]) -> Result<(), String>;
+    /// The tasks of every list matching a filter, in list order
+    fn query(&self, filter: &Filter) -> Result<Vec<Task>, String>;
+    /// The names of the lists besides the default one, found by `load` or created since
+    fn lists(&self) -> Vec<String>;
+    /// Adds an empty named list, it stays once its last task is moved or deleted
+    fn create_list(&mut self, name: &str) -> Result<(), String>;
+    /// Whether something else wrote to the store since the last `load` or `save`
+    fn changed(&self) -> Result<bool, String>;
+    /// Like `changed`, except that what a failed `load` read only counts once it changes
+    /// again, so a store that can't be read isn't read on every poll
+    fn reload_needed(&self) -> Result<bool, String> {
+        self.changed()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// `storage_file` and `archive_file`
+    #[default]
+    Json,
+    /// `database_file`
+    Sqlite,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Some(Backend::Json),
+            "sqlite" => Some(Backend::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+/// Where a task is kept
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Location {
+    /// the store of the git repo hourglass was started in
+    Repo,
+    /// the store in the data dir, the same wherever hourglass is started
+    #[default]
+    Global,
+}
+
+impl Location {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Location::Repo => "repo",
+            Location::Global => "global",
+        }
+    }
+}
+
+/// Which stores the tasks tab shows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Repo,
+    Global,
+    #[default]
+    All,
+}
+
+impl Scope {
+    pub const NAMES: [&'static str; 3] = ["repo", "global", "all"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "repo" => Some(Scope::Repo),
+            "global" => Some(Scope::Global),
+            "all" => Some(Scope::All),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Repo => "repo",
+            Scope::Global => "global",
+            Scope::All => "all",
+        }
+    }
+
+    pub fn includes(&self, location: Location) -> bool {
+        match self {
+            Scope::Repo => location == Location::Repo,
+            Scope::Global => location == Location::Global,
+            Scope::All => true,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Scope::Repo => Scope::Global,
+            Scope::Global => Scope::All,
+            Scope::All => Scope::Repo,
+        }
+    }
+}
+
+/// A store and the directory its files are in
+pub struct LocatedStore {
+    pub location: Location,
+    pub dir: PathBuf,
+    pub store: Box<dyn Store>,
+    // the tasks and the archive as last loaded or saved, the base of a merge with what
+    // something else wrote to the store since
+    pub synced: (Vec<Task>, Vec<Task>),
+}
+
+/// The global store, and the store of the current project when there is one
+pub fn locate(config: &Config) -> Vec<LocatedStore> {
+    let global = data_dir().map(|dir| dir.join("hourglass"));
+    let repo = env::current_dir()
+        .ok()
+        .and_then(|dir| find_project_dir(&dir, config));
+
+    let mut stores: Vec<LocatedStore> = [(Location::Global, global), (Location::Repo, repo)]
+        .into_iter()
+        .filter_map(|(location, dir)| dir.map(|dir| (location, dir)))
+        .map(|(location, dir)| LocatedStore {
+            location,
+            store: open(config, &dir),
+            dir,
+            synced: (vec![], vec![]),
+        })
+        .collect();
+
+    // without a data dir or a repo, the current directory is all there is
+    if stores.is_empty() {
+        stores.push(LocatedStore {
+            location: Location::Repo,
+            dir: PathBuf::from("."),
+            store: open(config, Path::new(".")),
+            synced: (vec![], vec![]),
+        });
+    }
+
+    stores
+}
+
+/// The store of the configured backend in `dir`, nothing is read before `Store::load`
+pub fn open(config: &Config, dir: &Path) -> Box<dyn Store> {
+    match config.backend {
+        Backend::Json => Box::new(JsonStore::new(config, dir)),
+        Backend::Sqlite => Box::new(SqliteStore::new(dir.join(&config.database_file))),
+    }
+}
+
+/// An advisory lock on the store in `dir`, released when the file is dropped. A directory
+/// without task files isn't locked, so no lock file is left where there are no tasks
+pub fn lock(dir: &Path, config: &Config) -> Result<Option<File>, String> {
+    if !has_task_files(dir, config) {
+        return Ok(None);
+    }
+
+    let path = dir.join(LOCK_FILE);
+    let error = |err: std::io::Error| format!("unable to lock {}: {}", path.display(), err);
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(error)?;
+
+    file.lock().map_err(error)?;
+
+    Ok(Some(file))
+}
+
+// the closest directory with task files up to the root of the git repo, or the root itself.
+// Outside a repo only the current directory is looked at
+fn find_project_dir(dir: &Path, config: &Config) -> Option<PathBuf> {
+    let git_root = dir.ancestors().find(|dir| dir.join(".git").exists());
+
+    for dir in dir.ancestors() {
+        if has_task_files(dir, config) {
+            return Some(dir.to_path_buf());
+        }
+
+        if Some(dir) == git_root || git_root.is_none() {
+            break;
+        }
+    }
+
+    git_root.map(Path::to_path_buf)
+}
+
+fn has_task_files(dir: &Path, config: &Config) -> bool {
+    let is_task_list = |path: &Path| {
+        path.extension()
+            .is_some_and(|extension| extension == HOURGLASS_EXTENSION)
+    };
+
+    [
+        &config.storage_file,
+        &config.archive_file,
+        &config.database_file,
+    ]
+    .iter()
+    .any(|file| dir.join(file).exists())
+        || fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .any(|entry| is_task_list(&entry.path()))
+        })
+}
+
+/// How the task files are written, both are read whatever the setting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageFormat {
+    /// a single JSON array on one line
+    #[default]
+    Compact,
+    /// one task per line sorted by uuid, without ids, so git diffs and merges them task by task
+    Lines,
+}
+
+impl StorageFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "compact" => Some(StorageFormat::Compact),
+            "lines" => Some(StorageFormat::Lines),
+            _ => None,
+        }
+    }
+
+    /// The format `content` was written in, `None` for an empty file
+    pub fn detect(content: &str) -> Option<Self> {
+        match content.trim_start().chars().next()? {
+            '[' => Some(StorageFormat::Compact),
+            _ => Some(StorageFormat::Lines),
+        }
+    }
+}
+
+pub fn serialize(tasks: &[&Task], format: StorageFormat) -> String {
+    match format {
+        StorageFormat::Compact => serde_json::to_string(tasks).unwrap(),
+        StorageFormat::Lines => {
+            let mut tasks = tasks.to_vec();
+            tasks.sort_by_key(|task| task.uuid);
+
+            tasks
+                .into_iter()
+                .map(|task| format!("{}\n", to_line(task)))
+                .collect()
+        }
+    }
+}
+
+// the keys of a `Value` are sorted, so a line only changes where the task did
+fn to_line(task: &Task) -> String {
+    let mut value = serde_json::to_value(task).unwrap();
+
+    if let Value::Object(fields) = &mut value {
+        // ids are given when the file is read, two clones would otherwise pick the same one
+        fields.remove("id");
+    }
+
+    value.to_string()
+}
+
+/// Either format, an empty file is an empty list. Tasks from the lines format have no id yet
+pub fn deserialize(content: &str) -> Result<Vec<Task>, String> {
+    match StorageFormat::detect(content) {
+        None => return Ok(vec![]),
+        Some(StorageFormat::Compact) => {
+            return serde_json::from_str(content).map_err(|err| err.to_string())
+        }
+        Some(StorageFormat::Lines) => {}
+    }
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| format!("line {}: {}", i + 1, err))
+        })
+        .collect()
+}
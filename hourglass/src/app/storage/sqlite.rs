@@ -0,0 +1,408 @@
+use chrono::Utc;
+use rusqlite::{params, params_from_iter, Connection};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::Store;
+use crate::app::command::{Filter, Status};
+use crate::app::task::Task;
+
+// `data` holds the whole task, the other columns are copies of it for the indexes
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tasks (
+    uuid TEXT PRIMARY KEY,
+    id INTEGER NOT NULL,
+    archived INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    completed INTEGER NOT NULL,
+    project TEXT,
+    priority TEXT,
+    due TEXT,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tasks_project ON tasks (archived, project);
+CREATE INDEX IF NOT EXISTS tasks_priority ON tasks (archived, priority);
+CREATE INDEX IF NOT EXISTS tasks_completed ON tasks (archived, completed);
+CREATE INDEX IF NOT EXISTS tasks_due ON tasks (archived, due);
+
+CREATE TABLE IF NOT EXISTS task_tags (
+    uuid TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (uuid, tag)
+);
+CREATE INDEX IF NOT EXISTS task_tags_tag ON task_tags (tag);
+
+-- the named lists, `data` says which one a task is in
+CREATE TABLE IF NOT EXISTS lists (
+    name TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uuid TEXT NOT NULL,
+    change TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    data TEXT
+);
+CREATE INDEX IF NOT EXISTS history_uuid ON history (uuid);
+
+CREATE TRIGGER IF NOT EXISTS history_no_update BEFORE UPDATE ON history
+BEGIN
+    SELECT RAISE(ABORT, 'the history is append-only');
+END;
+CREATE TRIGGER IF NOT EXISTS history_no_delete BEFORE DELETE ON history
+BEGIN
+    SELECT RAISE(ABORT, 'the history is append-only');
+END;
+";
+
+// what a row of `history` says happened to the task, `data` is the task after the change
+#[derive(Clone, Copy)]
+enum Change {
+    Added,
+    Modified,
+    Archived,
+    Restored,
+    // `data` is null
+    Deleted,
+}
+
+impl Change {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Modified => "modified",
+            Change::Archived => "archived",
+            Change::Restored => "restored",
+            Change::Deleted => "deleted",
+        }
+    }
+}
+
+/// The task list and the archive in a SQLite database. Saving only writes the tasks that
+/// changed, and every change is appended to the `history` table
+pub struct SqliteStore {
+    path: PathBuf,
+    // opened by `load` when the database exists, by `save` otherwise
+    connection: Option<Connection>,
+    // the JSON of every stored task and whether it is archived, to find what changed
+    stored: BTreeMap<Uuid, (String, bool)>,
+    lists: BTreeSet<String>,
+    // changes when another connection writes to the database, see `changed`
+    data_version: i64,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf) -> Self {
+        SqliteStore {
+            path,
+            connection: None,
+            stored: BTreeMap::new(),
+            lists: BTreeSet::new(),
+            data_version: 0,
+        }
+    }
+
+    fn open(&mut self) -> Result<(), String> {
+        let error = |err: String| format!("{}: {}", self.path.display(), err);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|err| error(err.to_string()))?;
+        }
+
+        let connection = Connection::open(&self.path).map_err(|err| error(err.to_string()))?;
+
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|err| error(err.to_string()))?;
+
+        self.connection = Some(connection);
+        self.data_version = self.data_version()?;
+
+        Ok(())
+    }
+
+    fn data_version(&self) -> Result<i64, String> {
+        self.connection()?
+            .query_row("PRAGMA data_version", [], |row| row.get(0))
+            .map_err(to_string)
+    }
+
+    fn connection(&self) -> Result<&Connection, String> {
+        self.connection
+            .as_ref()
+            .ok_or_else(|| format!("{} is not open", self.path.display()))
+    }
+
+    fn read(&self, sql: &str, params: &[String]) -> Result<Vec<Task>, String> {
+        let mut statement = self.connection()?.prepare(sql).map_err(to_string)?;
+
+        let rows = statement
+            .query_map(params_from_iter(params), |row| row.get::<_, String>(0))
+            .map_err(to_string)?;
+
+        rows.map(|data| {
+            let data = data.map_err(to_string)?;
+
+            serde_json::from_str(&data).map_err(|err| format!("{}: {}", self.path.display(), err))
+        })
+        .collect()
+    }
+
+    fn read_lists(&self) -> Result<BTreeSet<String>, String> {
+        let mut statement = self
+            .connection()?
+            .prepare("SELECT name FROM lists")
+            .map_err(to_string)?;
+
+        let names = statement
+            .query_map([], |row| row.get(0))
+            .map_err(to_string)?;
+
+        names.map(|name| name.map_err(to_string)).collect()
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&mut self) -> Result<(Vec<Task>, Vec<Task>), String> {
+        // created once there is a task to save
+        if !self.path.exists() {
+            return Ok((vec![], vec![]));
+        }
+
+        self.open()?;
+
+        let tasks = self.read(
+            "SELECT data FROM tasks WHERE archived = 0 ORDER BY position",
+            &[],
+        )?;
+        let archive = self.read("SELECT data FROM tasks WHERE archived = 1", &[])?;
+        self.lists = self.read_lists()?;
+
+        self.stored = tasks
+            .iter()
+            .map(|task| (task, false))
+            .chain(archive.iter().map(|task| (task, true)))
+            .map(|(task, archived)| (task.uuid, (to_json(task), archived)))
+            .collect();
+
+        Ok((tasks, archive))
+    }
+
+    fn save(&mut self, tasks: &[&Task], archive: &[&Task]) -> Result<(), String> {
+        let now = Utc::now().to_rfc3339();
+        let mut stored = BTreeMap::new();
+        let mut changes = vec![];
+
+        for (&task, archived) in tasks
+            .iter()
+            .map(|task| (task, false))
+            .chain(archive.iter().map(|task| (task, true)))
+        {
+            let data = to_json(task);
+
+            let change = match self.stored.get(&task.uuid) {
+                None => Some(Change::Added),
+                Some((_, was_archived)) if *was_archived != archived => Some(match archived {
+                    true => Change::Archived,
+                    false => Change::Restored,
+                }),
+                Some((stored_data, _)) if *stored_data != data => Some(Change::Modified),
+                Some(_) => None,
+            };
+
+            if let Some(change) = change {
+                changes.push((change, task, archived, data.clone()));
+            }
+
+            stored.insert(task.uuid, (data, archived));
+        }
+
+        let deleted: Vec<Uuid> = self
+            .stored
+            .keys()
+            .filter(|uuid| !stored.contains_key(uuid))
+            .copied()
+            .collect();
+
+        if changes.is_empty() && deleted.is_empty() {
+            return Ok(());
+        }
+
+        if self.connection.is_none() {
+            self.open()?;
+        }
+
+        let connection = self.connection()?;
+        let transaction = connection.unchecked_transaction().map_err(to_string)?;
+        let mut lists = vec![];
+
+        for (change, task, archived, data) in changes {
+            let uuid = task.uuid.to_string();
+
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO tasks
+                     (uuid, id, archived, position, description, completed, project, priority, due, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        uuid,
+                        task.id,
+                        archived,
+                        task.position,
+                        task.description,
+                        task.completed,
+                        task.project,
+                        task.priority.map(|priority| priority.as_str()),
+                        task.due.map(|due| due.to_rfc3339()),
+                        data,
+                    ],
+                )
+                .map_err(to_string)?;
+
+            transaction
+                .execute("DELETE FROM task_tags WHERE uuid = ?1", params![uuid])
+                .map_err(to_string)?;
+
+            for tag in task.tags.iter() {
+                transaction
+                    .execute(
+                        "INSERT OR IGNORE INTO task_tags (uuid, tag) VALUES (?1, ?2)",
+                        params![uuid, tag],
+                    )
+                    .map_err(to_string)?;
+            }
+
+            if let Some(list) = &task.list {
+                transaction
+                    .execute(
+                        "INSERT OR IGNORE INTO lists (name) VALUES (?1)",
+                        params![list],
+                    )
+                    .map_err(to_string)?;
+                lists.push(list.clone());
+            }
+
+            transaction
+                .execute(
+                    "INSERT INTO history (uuid, change, changed_at, data) VALUES (?1, ?2, ?3, ?4)",
+                    params![uuid, change.as_str(), now, data],
+                )
+                .map_err(to_string)?;
+        }
+
+        for uuid in deleted {
+            let uuid = uuid.to_string();
+
+            transaction
+                .execute("DELETE FROM tasks WHERE uuid = ?1", params![uuid])
+                .map_err(to_string)?;
+            transaction
+                .execute("DELETE FROM task_tags WHERE uuid = ?1", params![uuid])
+                .map_err(to_string)?;
+            transaction
+                .execute(
+                    "INSERT INTO history (uuid, change, changed_at) VALUES (?1, ?2, ?3)",
+                    params![uuid, Change::Deleted.as_str(), now],
+                )
+                .map_err(to_string)?;
+        }
+
+        transaction.commit().map_err(to_string)?;
+
+        self.stored = stored;
+        self.lists.extend(lists);
+
+        Ok(())
+    }
+
+    // the words are matched again on the tasks, SQLite only lowercases ASCII letters
+    fn query(&self, filter: &Filter) -> Result<Vec<Task>, String> {
+        if self.connection.is_none() {
+            return Ok(vec![]);
+        }
+
+        let mut conditions = vec![String::from("archived = 0")];
+        let mut params = vec![];
+
+        for tag in filter.tags.iter() {
+            conditions.push(String::from(
+                "uuid IN (SELECT uuid FROM task_tags WHERE tag = ?)",
+            ));
+            params.push(tag.clone());
+        }
+
+        for tag in filter.excluded_tags.iter() {
+            conditions.push(String::from(
+                "uuid NOT IN (SELECT uuid FROM task_tags WHERE tag = ?)",
+            ));
+            params.push(tag.clone());
+        }
+
+        if let Some(project) = &filter.project {
+            conditions.push(String::from("project = ?"));
+            params.push(project.clone());
+        }
+
+        if let Some(priority) = filter.priority {
+            conditions.push(String::from("priority = ?"));
+            params.push(priority.as_str().to_string());
+        }
+
+        if let Some(status) = filter.status {
+            conditions.push(String::from(match status {
+                Status::Pending => "completed = 0",
+                Status::Completed => "completed = 1",
+            }));
+        }
+
+        let sql = format!(
+            "SELECT data FROM tasks WHERE {} ORDER BY position",
+            conditions.join(" AND ")
+        );
+
+        let mut tasks = self.read(&sql, &params)?;
+        tasks.retain(|task| filter.matches(task));
+
+        Ok(tasks)
+    }
+
+    fn lists(&self) -> Vec<String> {
+        self.lists.iter().cloned().collect()
+    }
+
+    fn create_list(&mut self, name: &str) -> Result<(), String> {
+        if self.connection.is_none() {
+            self.open()?;
+        }
+
+        self.connection()?
+            .execute(
+                "INSERT OR IGNORE INTO lists (name) VALUES (?1)",
+                params![name],
+            )
+            .map_err(to_string)?;
+        self.lists.insert(name.to_string());
+
+        Ok(())
+    }
+
+    // the writes of this connection leave the data version as it is
+    fn changed(&self) -> Result<bool, String> {
+        match self.connection {
+            Some(_) => Ok(self.data_version()? != self.data_version),
+            None => Ok(self.path.exists()),
+        }
+    }
+}
+
+fn to_json(task: &Task) -> String {
+    serde_json::to_string(task).unwrap()
+}
+
+fn to_string(err: rusqlite::Error) -> String {
+    err.to_string()
+}
@@ -0,0 +1,250 @@
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::app::storage::Location;
+use crate::util::parse_date;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    #[serde(rename = "H")]
+    High,
+    #[serde(rename = "M")]
+    Medium,
+    #[serde(rename = "L")]
+    Low,
+}
+
+impl Priority {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "H" | "HIGH" => Some(Priority::High),
+            "M" | "MEDIUM" => Some(Priority::Medium),
+            "L" | "LOW" => Some(Priority::Low),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::High => "H",
+            Priority::Medium => "M",
+            Priority::Low => "L",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Task {
+    // the lines storage format leaves it out, it is given when the file is read
+    #[serde(default)]
+    pub id: i32,
+    // stays the same across imports and exports, unlike `id` which is per list
+    #[serde(default)]
+    pub uuid: Uuid,
+    pub description: String,
+    // free-form, multi-line text edited through $EDITOR
+    #[serde(default)]
+    pub notes: String,
+    // timestamped comments, as Taskwarrior has them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    pub completed: bool,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub due: Option<DateTime<Utc>>,
+    // where the task goes when the list isn't sorted, changed with `move_up` and `move_down`
+    #[serde(default)]
+    pub position: i32,
+    // `key:value` pairs from imported files that hourglass has no field for, written back on export
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, String>,
+    // the named list the task is in, `None` for the default one. The JSON store keeps each
+    // list in its own file, it is kept here too so restoring an archived task puts it back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list: Option<String>,
+    // which store the task was loaded from and is saved to, the files don't say
+    #[serde(skip)]
+    pub location: Location,
+    // the id in the store when it clashes with a task of the other store, `id` is then a new
+    // one that is only shown here, this one is saved back so the file doesn't change
+    #[serde(skip)]
+    pub stored_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Annotation {
+    pub entry: DateTime<Utc>,
+    pub description: String,
+}
+
+impl Task {
+    /// Whether the due date has passed, completed tasks are never overdue
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        !self.completed && self.due.is_some_and(|due| due < now)
+    }
+
+    pub fn set_completed(&mut self, completed: bool) {
+        if completed != self.completed {
+            self.completed = completed;
+            self.completed_at = completed.then(Utc::now);
+        }
+
+        self.modified_at = Utc::now();
+    }
+
+    pub fn new(id: i32, input: TaskInput) -> Self {
+        let time = Utc::now();
+
+        Task {
+            id,
+            uuid: Uuid::new_v4(),
+            description: input.description,
+            notes: String::new(),
+            annotations: vec![],
+            completed: false,
+            completed_at: None,
+            tags: input.tags,
+            project: input.project,
+            priority: input.priority,
+            due: input.due,
+            position: 0,
+            extensions: BTreeMap::new(),
+            list: None,
+            location: Location::default(),
+            stored_id: None,
+            created_at: time,
+            modified_at: time,
+        }
+    }
+
+    pub fn apply_input(&mut self, input: TaskInput) {
+        self.description = input.description;
+        self.tags = input.tags;
+        self.project = input.project;
+        self.priority = input.priority;
+        self.due = input.due;
+        self.modified_at = Utc::now();
+    }
+
+    /// The text that `parse_input` turns back into this task, used to pre-fill the command bar
+    pub fn to_input(&self) -> String {
+        let mut words = vec![self.description.clone()];
+
+        words.extend(self.tags.iter().map(|tag| format!("+{}", tag)));
+
+        if let Some(project) = &self.project {
+            words.push(format!("project:{}", project));
+        }
+
+        if let Some(priority) = &self.priority {
+            words.push(format!("priority:{}", priority.as_str()));
+        }
+
+        if let Some(due) = &self.due {
+            let due: DateTime<Local> = DateTime::from(*due);
+            words.push(format!("due:{}", due.format("%Y-%m-%d")));
+        }
+
+        words.join(" ")
+    }
+}
+
+/// What the user typed in the command bar, split into the description and the attributes
+#[derive(Default, Debug)]
+pub struct TaskInput {
+    pub description: String,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub priority: Option<Priority>,
+    pub due: Option<DateTime<Utc>>,
+}
+
+/// Attribute prefixes understood by `parse_input`
+pub const ATTRIBUTES: [&str; 3] = ["project:", "priority:", "due:"];
+
+/// Pulls `+tag`, `project:name`, `priority:H|M|L` and `due:<date>` out of the input,
+/// every other word is part of the description
+pub fn parse_input(input: &str) -> TaskInput {
+    let mut parsed = TaskInput::default();
+    let mut description = vec![];
+
+    for word in input.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            if !parsed.tags.iter().any(|t| t == tag) {
+                parsed.tags.push(tag.to_string());
+            }
+        } else if let Some(project) = word.strip_prefix("project:").filter(|p| !p.is_empty()) {
+            parsed.project = Some(project.to_string());
+        } else if let Some(priority) = word.strip_prefix("priority:").and_then(Priority::parse) {
+            parsed.priority = Some(priority);
+        } else if let Some(due) = word
+            .strip_prefix("due:")
+            .and_then(|due| parse_date(due, Local::now()))
+        {
+            parsed.due = Some(due);
+        } else {
+            description.push(word);
+        }
+    }
+
+    parsed.description = description.join(" ");
+
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        let input = parse_input("Call mom +family priority:h project:home due:2023-06-01 +phone");
+
+        assert_eq!(input.description, "Call mom");
+        assert_eq!(input.tags, ["family", "phone"]);
+        assert_eq!(input.project.as_deref(), Some("home"));
+        assert_eq!(input.priority, Some(Priority::High));
+        assert_eq!(input.due, parse_date("2023-06-01", Local::now()));
+    }
+
+    #[test]
+    fn invalid_attributes_stay_in_the_description() {
+        let input = parse_input("a + b project: priority:x due:never c");
+
+        assert_eq!(input.description, "a + b project: priority:x due:never c");
+        assert!(input.tags.is_empty());
+        assert_eq!(input.project, None);
+        assert_eq!(input.priority, None);
+        assert_eq!(input.due, None);
+    }
+
+    #[test]
+    fn repeated_tags_are_kept_once() {
+        assert_eq!(parse_input("+a +b +a").tags, ["a", "b"]);
+    }
+
+    #[test]
+    fn to_input_round_trips() {
+        let mut task = Task::new(1, parse_input("Write report +work priority:M project:q3"));
+        task.due = parse_date("2023-06-01", Local::now());
+
+        let input = parse_input(&task.to_input());
+
+        assert_eq!(input.description, task.description);
+        assert_eq!(input.tags, task.tags);
+        assert_eq!(input.project, task.project);
+        assert_eq!(input.priority, task.priority);
+        assert_eq!(input.due, task.due);
+    }
+}
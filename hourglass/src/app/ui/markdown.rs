@@ -0,0 +1,249 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use super::theme::Theme;
+
+/// Turns a markdown document (an issue body, a comment...) into styled lines
+/// that can be handed to a `Paragraph`
+pub fn render_markdown(text: &str, theme: &Theme) -> Vec<Line<'static>> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+
+    let mut renderer = Renderer {
+        theme: *theme,
+        ..Renderer::default()
+    };
+
+    for event in Parser::new_ext(text, options) {
+        renderer.handle(event);
+    }
+
+    renderer.finish()
+}
+
+#[derive(Default)]
+struct Renderer {
+    theme: Theme,
+    lines: Vec<Line<'static>>,
+    current: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    // one entry per nested list, `Some(n)` for ordered lists
+    lists: Vec<Option<u64>>,
+    links: Vec<String>,
+    in_code_block: bool,
+}
+
+impl Renderer {
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if self.in_code_block {
+                    // code blocks keep their own line breaks
+                    for (i, line) in text.lines().enumerate() {
+                        if i > 0 {
+                            self.flush();
+                        }
+                        self.push(format!("  {}", line), self.theme.code);
+                    }
+                    if text.ends_with('\n') {
+                        self.flush();
+                    }
+                } else {
+                    self.push(text.to_string(), self.style());
+                }
+            }
+            Event::Code(code) => self.push(code.to_string(), self.theme.code),
+            Event::Html(html) => self.push(html.to_string(), self.style()),
+            Event::SoftBreak => self.push(String::from(" "), self.style()),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.flush();
+                self.lines
+                    .push(Line::from(Span::styled("─".repeat(20), self.theme.muted)));
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+
+                self.push(String::from(marker), self.theme.checkbox);
+            }
+            Event::FootnoteReference(name) => self.push(format!("[{}]", name), self.style()),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {}
+            Tag::Heading(level, _, _) => {
+                self.flush();
+
+                let prefix = match level {
+                    HeadingLevel::H1 => "# ",
+                    HeadingLevel::H2 => "## ",
+                    HeadingLevel::H3 => "### ",
+                    _ => "#### ",
+                };
+
+                let style = self.theme.heading;
+
+                self.push(String::from(prefix), style);
+                self.styles.push(style);
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                self.styles.push(self.style().patch(self.theme.quote));
+                self.push(String::from("│ "), self.style());
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                self.in_code_block = true;
+
+                if let CodeBlockKind::Fenced(lang) = kind {
+                    if !lang.is_empty() {
+                        self.lines.push(Line::from(Span::styled(
+                            format!("  {}", lang),
+                            self.theme.muted,
+                        )));
+                    }
+                }
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        let bullet = format!("{}{}. ", indent, n);
+                        *n += 1;
+                        bullet
+                    }
+                    _ => format!("{}• ", indent),
+                };
+
+                self.push(bullet, self.theme.muted);
+            }
+            Tag::Emphasis => self
+                .styles
+                .push(self.style().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.styles.push(self.style().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self
+                .styles
+                .push(self.style().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link(_, url, _) => {
+                self.links.push(url.to_string());
+                self.styles.push(
+                    self.style()
+                        .patch(self.theme.link)
+                        .add_modifier(Modifier::UNDERLINED),
+                );
+            }
+            Tag::Image(_, url, _) => {
+                self.links.push(url.to_string());
+                self.styles.push(self.style().patch(self.theme.link));
+                self.push(String::from("🖼 "), self.style());
+            }
+            Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead | Tag::TableRow => {
+                self.flush()
+            }
+            Tag::TableCell => self.push(String::from("| "), self.style()),
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                self.flush();
+                self.blank_line();
+            }
+            Tag::Heading(..) => {
+                self.styles.pop();
+                self.flush();
+                self.blank_line();
+            }
+            Tag::BlockQuote => {
+                self.styles.pop();
+                self.flush();
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.in_code_block = false;
+                self.blank_line();
+            }
+            Tag::List(_) => {
+                self.flush();
+                self.lists.pop();
+
+                if self.lists.is_empty() {
+                    self.blank_line();
+                }
+            }
+            Tag::Item => self.flush(),
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough => {
+                self.styles.pop();
+            }
+            Tag::Link(..) | Tag::Image(..) => {
+                self.styles.pop();
+
+                if let Some(url) = self.links.pop() {
+                    self.push(format!(" ({})", url), self.theme.muted);
+                }
+            }
+            Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead | Tag::TableRow => {
+                self.flush()
+            }
+            Tag::TableCell => self.push(String::from(" "), self.style()),
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn push(&mut self, text: String, style: Style) {
+        self.current.push(Span::styled(text, style));
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            let spans = std::mem::take(&mut self.current);
+            self.lines.push(Line::from(spans));
+        }
+    }
+
+    // avoid stacking up empty lines between blocks
+    fn blank_line(&mut self) {
+        if !self.lists.is_empty() {
+            return;
+        }
+
+        if let Some(last) = self.lines.last() {
+            if last.width() > 0 {
+                self.lines.push(Line::from(""));
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        self.flush();
+
+        while let Some(last) = self.lines.last() {
+            if last.width() == 0 {
+                self.lines.pop();
+            } else {
+                break;
+            }
+        }
+
+        self.lines
+    }
+}
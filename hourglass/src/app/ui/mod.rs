@@ -1,26 +1,88 @@
 use chrono::Utc;
 use ratatui::{
     backend::Backend,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     symbols::line,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, Padding, Paragraph, Row, Table, Tabs, Wrap},
+    widgets::{
+        Block, Borders, Cell, Clear, List, ListItem, ListState, Padding, Paragraph, Row, Table,
+        Tabs, Wrap,
+    },
     Frame,
 };
 use std::cmp;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-use crate::app::{Action, Hourglass, View, TIME_FORMAT};
+use crate::app::keymap::{KeyAction, KeyContext};
+use crate::app::storage::Scope;
+use crate::app::task::Task;
+use crate::app::{Action, Focus, Hourglass, Message, View, COMMAND_LINE_KEYS};
 use crate::util::{convert_utc_to_local, format_time};
 
+mod markdown;
+pub mod theme;
+
+use markdown::render_markdown;
+use theme::Theme;
+
+// below this the layout can't fit, a message is shown instead
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 8;
+
+// terminals at least this wide show the details next to the table, narrower ones stack them
+const WIDE_LAYOUT_MIN_WIDTH: u16 = 120;
+// below this the details only show up in a popup, when they are focused
+const STACKED_LAYOUT_MIN_WIDTH: u16 = 60;
+
+// ages look like `12min` at most
+const AGE_COLUMN_WIDTH: u16 = 5;
+
+/// How the table and the details pane share the main area
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayoutMode {
+    SideBySide,
+    Stacked,
+    // the table only, the details open in a popup when focused
+    Narrow,
+}
+
+impl LayoutMode {
+    pub fn for_width(width: u16) -> Self {
+        if width >= WIDE_LAYOUT_MIN_WIDTH {
+            LayoutMode::SideBySide
+        } else if width >= STACKED_LAYOUT_MIN_WIDTH {
+            LayoutMode::Stacked
+        } else {
+            LayoutMode::Narrow
+        }
+    }
+}
+
 struct Field {
     name: String,
     value: String,
 }
 
 pub fn build_ui<B: Backend>(f: &mut Frame<B>, app: &mut Hourglass) {
+    let size = f.size();
+
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        // nothing is clickable until the terminal is big enough again
+        app.areas = Default::default();
+
+        let message = Paragraph::new(format!(
+            "Terminal too small, {}x{} at least",
+            MIN_WIDTH, MIN_HEIGHT
+        ))
+        .wrap(Wrap { trim: true });
+
+        f.render_widget(message, size);
+
+        return;
+    }
+
     let rects = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
@@ -36,115 +98,297 @@ pub fn build_ui<B: Backend>(f: &mut Frame<B>, app: &mut Hourglass) {
 
     let titles = app.tabs.iter().cloned().map(Line::from).collect();
 
+    // same spacing as the `Tabs` widget: a space on each side of a title, then the divider
+    let mut x = rects[0].x;
+    app.areas.tabs = app
+        .tabs
+        .iter()
+        .map(|tab| {
+            let width = tab.width() as u16 + 2;
+            let area = Rect {
+                x,
+                y: rects[0].y,
+                width,
+                height: 1,
+            };
+            x += width + 1;
+
+            area
+        })
+        .collect();
+
     let tabs = Tabs::new(titles)
-        .style(
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::DIM),
-        )
-        .highlight_style(
-            Style::default()
-                .fg(Color::Yellow)
-                .remove_modifier(Modifier::DIM),
-        )
+        .style(app.theme.tab)
+        .highlight_style(app.theme.tab_selected)
         .divider(line::THICK_VERTICAL)
         .select(app.tab_index);
 
     f.render_widget(tabs, rects[0]);
 
-    match app.tab_index {
-        0 => render_tasks(app, rects.clone(), f),
-        1 => render_issues(app, rects.clone(), f),
-        _ => {}
+    match app.tabs[app.tab_index].as_str() {
+        "issues" => render_issues(app, rects.clone(), f),
+        "archive" => render_archive(app, rects.clone(), f),
+        // the tasks tab and the named lists
+        _ => render_tasks(app, rects.clone(), f),
     }
 
-    render_command(app, rects, f);
+    render_command(app, rects.clone(), f);
+    render_completion(app, rects, f);
+
+    if app.show_help {
+        render_help(app, f);
+    }
 }
 
 fn render_tasks<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Frame<B>) {
-    let task_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(rects[1]);
+    let (table_area, details_area) = split_main_area(app, rects[1]);
+
+    let visible = app.visible_tasks();
+    let now = Utc::now();
+
+    let id_width = visible
+        .iter()
+        .map(|&i| app.tasks[i].id.to_string().len() as u16 + 2)
+        .chain([2])
+        .max()
+        .unwrap_or_default();
 
-    let rows = app.tasks.iter().map(|task| {
+    let rows = visible.iter().map(|&i| {
+        let task = &app.tasks[i];
         let height = 1;
 
+        let marker = if app.marked.contains(&task.id) {
+            "● "
+        } else {
+            ""
+        };
+
         let cells = vec![
-            format!("{}", task.id),
+            format!("{}{}", marker, task.id),
             format!("{}", task.description),
             format_time(task.created_at, Utc::now()),
         ]
         .into_iter()
         .map(|c| Cell::from(c));
 
-        let mut style = Style::default();
-
-        if task.completed {
-            style = style
-                .add_modifier(Modifier::CROSSED_OUT)
-                .add_modifier(Modifier::DIM);
-        }
+        let style = if app.marked.contains(&task.id) {
+            app.theme.marked
+        } else if task.completed {
+            app.theme.completed
+        } else if task.is_overdue(now) {
+            app.theme.overdue
+        } else {
+            Style::default()
+        };
 
         Row::new(cells).height(height).style(style)
     });
 
-    let table = render_table(rows, vec!["ID", "Description", "Age"]);
+    let widths = column_widths(app, table_area, id_width);
+    let table = render_table(
+        rows,
+        vec!["ID", "Description", "Age"],
+        &widths,
+        is_table_focused(app),
+        &app.theme,
+    );
 
-    f.render_stateful_widget(table, task_layout[0], &mut app.table_state);
+    f.render_stateful_widget(table, table_area, &mut app.table_state);
+    set_table_areas(app, table_area, details_area);
 
-    // display details for issue selected
-    if let Some(i) = app.table_state.selected() {
+    // display details for task selected
+    if let (Some(details_area), Some(i)) = (details_area, app.selected_task_index()) {
         let selected_task = app.tasks.get(i);
 
         if let Some(task) = selected_task {
+            let (fields, notes) = task_details(app, task);
+
             render_details(
                 f,
-                task_layout.to_vec(),
+                app,
+                details_area,
                 vec![String::from("Name"), String::from("Value")],
-                vec![
-                    Field {
-                        name: String::from("ID"),
-                        value: task.id.to_string(),
-                    },
-                    Field {
-                        name: String::from("Description"),
-                        value: task.description.clone(),
-                    },
-                    Field {
-                        name: String::from("Age"),
-                        value: format_time(task.created_at, Utc::now()),
-                    },
-                    Field {
-                        name: String::from("Created at"),
-                        value: format!("{}", convert_utc_to_local(task.created_at, TIME_FORMAT)),
-                    },
-                    Field {
-                        name: String::from("Modified at"),
-                        value: format!("{}", convert_utc_to_local(task.modified_at, TIME_FORMAT)),
-                    },
-                ],
+                fields,
+                notes,
             );
         }
     }
 }
 
+// archived tasks, most recently completed first, with how long ago they were completed
+fn render_archive<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Frame<B>) {
+    let (table_area, details_area) = split_main_area(app, rects[1]);
+
+    let id_width = app
+        .archive
+        .iter()
+        .map(|task| task.id.to_string().len() as u16)
+        .chain([2])
+        .max()
+        .unwrap_or_default();
+
+    let rows = app.archive.iter().map(|task| {
+        let completed_at = task.completed_at.unwrap_or(task.modified_at);
+
+        let cells = vec![
+            task.id.to_string(),
+            task.description.clone(),
+            format_time(completed_at, Utc::now()),
+        ];
+
+        Row::new(cells).height(1)
+    });
+
+    let widths = column_widths(app, table_area, id_width);
+    let table = render_table(
+        rows,
+        vec!["ID", "Description", "Done"],
+        &widths,
+        is_table_focused(app),
+        &app.theme,
+    );
+
+    f.render_stateful_widget(table, table_area, &mut app.table_state);
+    set_table_areas(app, table_area, details_area);
+
+    if let (Some(details_area), Some(i)) = (details_area, app.table_state.selected()) {
+        if let Some(task) = app.archive.get(i) {
+            let (fields, notes) = task_details(app, task);
+
+            render_details(
+                f,
+                app,
+                details_area,
+                vec![String::from("Name"), String::from("Value")],
+                fields,
+                notes,
+            );
+        }
+    }
+}
+
+// the fields and the notes shown in the details pane, for tasks and archived tasks alike
+fn task_details(app: &Hourglass, task: &Task) -> (Vec<Field>, Vec<Line<'static>>) {
+    let mut notes = vec![];
+
+    if !task.notes.is_empty() {
+        notes.push(section_title(String::from("Notes"), &app.theme));
+        notes.extend(render_markdown(&task.notes, &app.theme));
+    }
+
+    if !task.annotations.is_empty() {
+        if !notes.is_empty() {
+            notes.push(Line::default());
+        }
+
+        notes.push(section_title(String::from("Annotations"), &app.theme));
+        notes.extend(task.annotations.iter().map(|annotation| {
+            Line::from(vec![
+                Span::styled(
+                    convert_utc_to_local(annotation.entry, &app.config.time_format),
+                    app.theme.muted,
+                ),
+                Span::raw(" "),
+                Span::raw(annotation.description.clone()),
+            ])
+        }));
+    }
+
+    let mut fields = vec![
+        Field {
+            name: String::from("ID"),
+            value: task.id.to_string(),
+        },
+        Field {
+            name: String::from("Description"),
+            value: task.description.clone(),
+        },
+        Field {
+            name: String::from("Tags"),
+            value: task.tags.join(", "),
+        },
+        Field {
+            name: String::from("Project"),
+            value: task.project.clone().unwrap_or_default(),
+        },
+        Field {
+            name: String::from("Store"),
+            value: task.location.as_str().to_string(),
+        },
+        Field {
+            name: String::from("Priority"),
+            value: task
+                .priority
+                .map(|priority| priority.as_str().to_string())
+                .unwrap_or_default(),
+        },
+        Field {
+            name: String::from("Due"),
+            value: task
+                .due
+                .map(|due| convert_utc_to_local(due, &app.config.time_format))
+                .unwrap_or_default(),
+        },
+        Field {
+            name: String::from("Age"),
+            value: format_time(task.created_at, Utc::now()),
+        },
+        Field {
+            name: String::from("Created at"),
+            value: format!(
+                "{}",
+                convert_utc_to_local(task.created_at, &app.config.time_format)
+            ),
+        },
+        Field {
+            name: String::from("Modified at"),
+            value: format!(
+                "{}",
+                convert_utc_to_local(task.modified_at, &app.config.time_format)
+            ),
+        },
+    ];
+
+    if let Some(completed_at) = task.completed_at {
+        fields.push(Field {
+            name: String::from("Completed at"),
+            value: convert_utc_to_local(completed_at, &app.config.time_format),
+        });
+    }
+
+    (fields, notes)
+}
+
 fn render_issues<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Frame<B>) {
-    let issue_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(rects[1]);
+    let (table_area, details_area) = split_main_area(app, rects[1]);
+
+    let id_width = app
+        .issues
+        .iter()
+        .map(|issue| issue.number.to_string().len() as u16 + 1)
+        .max()
+        .unwrap_or(1);
 
     let rows = app.issues.iter().map(|issue| {
         let height = 1;
 
+        let mut title = vec![Span::from(issue.title.clone())];
+
+        for label in issue.labels.iter() {
+            title.push(Span::from(" "));
+            title.push(Span::styled(
+                format!("[{}]", label.name),
+                app.theme.issue_label,
+            ));
+        }
+
         let cells = vec![
-            Span::styled(
+            Line::from(Span::styled(
                 format!("#{}", issue.number),
-                Style::default().fg(Color::Green),
-            ),
-            Span::from(format!("{}", issue.title)),
-            Span::from(format_time(issue.created_at, Utc::now())),
+                app.theme.issue_number,
+            )),
+            Line::from(title),
+            Line::from(format_time(issue.created_at, Utc::now())),
         ]
         .into_iter()
         .map(|c| Cell::from(c));
@@ -154,67 +398,120 @@ fn render_issues<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Fram
         Row::new(cells).height(height).style(style)
     });
 
-    let table = render_table(rows, vec!["#", "Title", "Age"]);
+    let widths = column_widths(app, table_area, id_width);
+    let table = render_table(
+        rows,
+        vec!["#", "Title", "Age"],
+        &widths,
+        is_table_focused(app),
+        &app.theme,
+    );
 
-    f.render_stateful_widget(table, issue_layout[0], &mut app.table_state);
+    f.render_stateful_widget(table, table_area, &mut app.table_state);
+    set_table_areas(app, table_area, details_area);
 
     // display details for issue selected
-    if let Some(i) = app.table_state.selected() {
+    if let (Some(details_area), Some(i)) = (details_area, app.table_state.selected()) {
         let selected_issue = app.issues.get(i);
 
         if let Some(issue) = selected_issue {
-            let issue_body = match issue.body.clone() {
-                Some(body) => body,
-                _ => String::new(),
-            };
+            let mut body = vec![section_title(String::from("Body"), &app.theme)];
+            body.extend(render_markdown(
+                issue.body.as_deref().unwrap_or_default(),
+                &app.theme,
+            ));
+
+            body.push(Line::from(""));
+            body.push(section_title(
+                format!("Comments ({})", app.comments.len()),
+                &app.theme,
+            ));
+
+            for comment in app.comments.iter() {
+                body.push(Line::from(""));
+                body.push(Line::from(vec![
+                    Span::styled(
+                        comment.user.login.clone(),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(
+                            " commented {} ago",
+                            format_time(comment.created_at, Utc::now())
+                        ),
+                        app.theme.muted,
+                    ),
+                ]));
+                body.extend(render_markdown(
+                    comment.body.as_deref().unwrap_or_default(),
+                    &app.theme,
+                ));
+            }
+
+            let fields = vec![
+                Field {
+                    name: String::from("Number"),
+                    value: issue.number.to_string(),
+                },
+                Field {
+                    name: String::from("Author"),
+                    value: issue.user.login.clone(),
+                },
+                Field {
+                    name: String::from("Title"),
+                    value: issue.title.clone(),
+                },
+                Field {
+                    name: String::from("Labels"),
+                    value: issue
+                        .labels
+                        .iter()
+                        .map(|label| label.name.clone())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                },
+                Field {
+                    name: String::from("Created at"),
+                    value: format!(
+                        "{}",
+                        convert_utc_to_local(issue.created_at, &app.config.time_format)
+                    ),
+                },
+                Field {
+                    name: String::from("Modified at"),
+                    value: format!(
+                        "{}",
+                        convert_utc_to_local(issue.updated_at, &app.config.time_format)
+                    ),
+                },
+                Field {
+                    name: String::from("Link"),
+                    value: issue.html_url.clone(),
+                },
+            ];
 
             render_details(
                 f,
-                issue_layout.to_vec(),
+                app,
+                details_area,
                 vec![String::from("Name"), String::from("Value")],
-                vec![
-                    Field {
-                        name: String::from("Number"),
-                        value: issue.number.to_string(),
-                    },
-                    Field {
-                        name: String::from("Author"),
-                        value: issue.user.login.clone(),
-                    },
-                    Field {
-                        name: String::from("Title"),
-                        value: issue.title.clone(),
-                    },
-                    Field {
-                        name: String::from("Body"),
-                        value: issue_body,
-                    },
-                    Field {
-                        name: String::from("Created at"),
-                        value: format!("{}", convert_utc_to_local(issue.created_at, TIME_FORMAT)),
-                    },
-                    Field {
-                        name: String::from("Modified at"),
-                        value: format!("{}", convert_utc_to_local(issue.updated_at, TIME_FORMAT)),
-                    },
-                    Field {
-                        name: String::from("Link"),
-                        value: issue.html_url.clone(),
-                    },
-                ],
+                fields,
+                body,
             );
         }
     }
 }
 
 fn render_command<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Frame<B>) {
-    let position = get_cursor_position(app.command_input.as_str());
+    let input = app.command_input.as_str();
+    // the cursor can be anywhere in the line, not only at the end of the text
+    let position = get_cursor_position(&input[..app.command_input.pos()]) as u16;
 
-    f.set_cursor(
-        // clamp the position of the cursor to the width of the command container
-        cmp::min(rects[2].x + 1 + position as u16, rects[2].width - 2),
-        rects[2].y + 1,
-    );
+    // scroll horizontally just enough to keep the cursor inside the command container
+    let inner_width = cmp::max(rects[2].width.saturating_sub(2), 1);
+    let scroll = (position + 1).saturating_sub(inner_width);
+
+    f.set_cursor(rects[2].x + 1 + position - scroll, rects[2].y + 1);
 
     let mut title = String::from("Command");
 
@@ -222,26 +519,259 @@ fn render_command<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Fra
         View::Task(action) => match action {
             Action::Add => title.push_str(" - Add task"),
             Action::Update => title.push_str(" - Update task"),
-            _ => {}
+            Action::Command => title.push_str(" - Run command"),
+            _ => {
+                if !app.marked.is_empty() {
+                    title.push_str(&format!(" - {} marked", app.marked.len()));
+                }
+
+                if let Some(filter) = &app.filter {
+                    title.push_str(&format!(" - filter: {}", filter.source));
+                }
+
+                if let Some(sort_key) = &app.sort_key {
+                    title.push_str(&format!(" - sort: {}", sort_key.as_str()));
+                }
+
+                if app.scope != Scope::All {
+                    title.push_str(&format!(" - scope: {}", app.scope.as_str()));
+                }
+            }
         },
-        View::Issues(_action) => {}
+        View::Issues(action) | View::Archive(action) => {
+            if let Action::Command = action {
+                title.push_str(" - Run command")
+            }
+        }
     }
-    let command = Block::default().borders(Borders::ALL).title(title);
+
+    // errors are shown in the title so the command that caused them stays editable
+    let mut title = vec![Span::styled(title, app.theme.command_title)];
+
+    let text = match &app.message {
+        Some(Message::Error(error)) => {
+            title.push(Span::styled(" - ", app.theme.command_title));
+            title.push(Span::styled(error.clone(), app.theme.error));
+
+            Text::from(input)
+        }
+        Some(Message::Info(info)) if input.is_empty() => Text::styled(info.clone(), app.theme.info),
+        _ => Text::from(input),
+    };
+
+    let command = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.command)
+        .style(app.theme.command)
+        .title(Line::from(title));
 
     f.render_widget(
-        Paragraph::new(Text::from(app.command_input.as_str()))
-            .block(command)
-            // we use saturating_sub here to prevent overflow when the command input is empty
-            .scroll((0, ((position + 3) as u16).saturating_sub(rects[2].width))),
+        Paragraph::new(text).block(command).scroll((0, scroll)),
         rects[2],
     );
+
+    // drawn on the bottom border, inside the corners
+    let hint_area = Rect {
+        x: rects[2].x + 1,
+        width: rects[2].width.saturating_sub(2),
+        ..rects[2]
+    };
+
+    let hint = Block::default()
+        .title(key_hints(app, hint_area.width as usize))
+        .title_on_bottom()
+        .title_alignment(Alignment::Right);
+
+    f.render_widget(hint, hint_area);
+}
+
+// a few keys for what can be done right now, from the same tables the input handlers use
+fn key_hints<'a>(app: &Hourglass, max_width: usize) -> Line<'a> {
+    let hints: Vec<(String, String)> = match app.key_context() {
+        Some(context) => {
+            let global = app.keymap.bindings_by_action(KeyContext::Global);
+            let global = global.into_iter().filter(|(action, _)| {
+                matches!(
+                    action,
+                    KeyAction::Command | KeyAction::Help | KeyAction::Quit
+                )
+            });
+
+            app.keymap
+                .bindings_by_action(context)
+                .into_iter()
+                .chain(global)
+                .map(|(action, notations)| {
+                    (notations[0].to_string(), action.name().replace('_', " "))
+                })
+                .collect()
+        }
+        None => {
+            let mut hints: Vec<(String, String)> = vec![];
+
+            for (_, key, description, _) in COMMAND_LINE_KEYS.iter() {
+                match hints.last_mut() {
+                    Some((keys, last)) if last == description => {
+                        keys.push('/');
+                        keys.push_str(key);
+                    }
+                    _ => hints.push((key.to_string(), description.to_string())),
+                }
+            }
+
+            hints
+        }
+    };
+
+    let mut spans = vec![];
+    let mut width = 0;
+
+    for (key, description) in hints {
+        let hint_width = key.width() + description.width() + 3;
+
+        // drop the hints that don't fit instead of cutting one in the middle
+        if width + hint_width > max_width {
+            break;
+        }
+
+        width += hint_width;
+
+        spans.push(Span::styled(key, app.theme.command_title));
+        spans.push(Span::styled(format!(" {}  ", description), app.theme.muted));
+    }
+
+    Line::from(spans)
+}
+
+// a centered popup with every binding of the current view, then the global ones
+fn render_help<B: Backend>(app: &mut Hourglass, f: &mut Frame<B>) {
+    let context = match app.key_context() {
+        Some(context) => context,
+        None => return,
+    };
+
+    let mut lines = vec![];
+
+    for section in [context, KeyContext::Global] {
+        let bindings = app.keymap.bindings_by_action(section);
+
+        if bindings.is_empty() {
+            continue;
+        }
+
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+
+        lines.push(section_title(
+            format!("{} keys", capitalize(section.name())),
+            &app.theme,
+        ));
+
+        for (action, notations) in bindings {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<16}", notations.join(" ")),
+                    app.theme.command_title,
+                ),
+                Span::from(action.description()),
+            ]));
+        }
+    }
+
+    let size = f.size();
+    let width = cmp::min(76, size.width.saturating_sub(4));
+    let height = cmp::min(lines.len() as u16 + 2, size.height.saturating_sub(2));
+
+    let area = Rect {
+        x: size.x + (size.width - width) / 2,
+        y: size.y + (size.height - height) / 2,
+        width,
+        height,
+    };
+
+    let content_height = lines.len() as u16;
+    let inner_height = height.saturating_sub(2);
+    app.help_scroll = cmp::min(app.help_scroll, content_height.saturating_sub(inner_height));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(app.theme.focused)
+        .title(Span::styled(" Help - Esc to close ", app.theme.focused));
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((app.help_scroll, 0)),
+        area,
+    );
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// the popup sits right above the command bar, aligned with the word being completed
+fn render_completion<B: Backend>(app: &mut Hourglass, rects: Vec<Rect>, f: &mut Frame<B>) {
+    let completion = match &app.completion {
+        Some(completion) => completion,
+        None => return,
+    };
+
+    let max_height = rects[2].y.saturating_sub(rects[1].y);
+    let height = cmp::min(completion.candidates.len() as u16 + 2, max_height);
+
+    let width = completion
+        .candidates
+        .iter()
+        .map(|candidate| candidate.display.width() as u16)
+        .max()
+        .unwrap_or(0)
+        + 4;
+
+    let word_offset = get_cursor_position(&app.command_input.as_str()[..completion.start]) as u16;
+    let x = cmp::min(
+        rects[2].x + word_offset,
+        (rects[2].x + rects[2].width).saturating_sub(width),
+    );
+
+    let area = Rect {
+        x,
+        y: rects[2].y.saturating_sub(height),
+        width: cmp::min(width, rects[2].width),
+        height,
+    };
+
+    let items: Vec<ListItem> = completion
+        .candidates
+        .iter()
+        .map(|candidate| ListItem::new(candidate.display.clone()))
+        .collect();
+
+    let mut state = ListState::default();
+    state.select(Some(completion.selected));
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(app.theme.completion_selected);
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
 }
 
 fn render_details<'a, B: ratatui::backend::Backend>(
     f: &mut Frame<B>,
-    rects: Vec<Rect>,
+    app: &mut Hourglass,
+    rect: Rect,
     columns: Vec<String>,
     fields: Vec<Field>,
+    body: Vec<Line<'a>>,
 ) {
     let gap = 2;
     let column_width = 12;
@@ -276,14 +806,8 @@ fn render_details<'a, B: ratatui::backend::Backend>(
         );
     }
 
-    lines.push(Line::from(Span::styled(
-        header_text,
-        Style::default().add_modifier(Modifier::DIM),
-    )));
-    lines.push(Line::from(Span::styled(
-        border_text,
-        Style::default().add_modifier(Modifier::DIM),
-    )));
+    lines.push(Line::from(Span::styled(header_text, app.theme.muted)));
+    lines.push(Line::from(Span::styled(border_text, app.theme.muted)));
 
     // ====================== END COLUMN NAME ======================
 
@@ -302,20 +826,205 @@ fn render_details<'a, B: ratatui::backend::Backend>(
 
     // ====================== END COLUMN FIELDS ========================
 
-    let details_block = Block::default().padding(Padding::horizontal(2));
-    let details_text = Paragraph::new(lines.clone()).wrap(Wrap { trim: true });
+    if !body.is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(body);
+    }
+
+    let title_style = if app.focus == Focus::Details {
+        app.theme.focused
+    } else {
+        app.theme.muted
+    };
+
+    let mut details_block = Block::default()
+        .title(Span::styled("Details", title_style))
+        .padding(Padding::horizontal(2));
+
+    // on narrow terminals the details are a popup over the table
+    if app.layout == LayoutMode::Narrow {
+        f.render_widget(Clear, rect);
+
+        details_block = details_block
+            .borders(Borders::ALL)
+            .border_style(title_style);
+    }
+
+    // the last column is reserved for the scrollbar
+    let text_area = details_block.inner(rect);
+    let text_area = Rect {
+        width: text_area.width.saturating_sub(1),
+        ..text_area
+    };
+
+    // remember the sizes so the input handlers can clamp the scroll offset
+    app.details_height = text_area.height;
+    app.details_content_height = wrapped_height(&lines, text_area.width);
+    app.details_scroll = cmp::min(
+        app.details_scroll,
+        app.details_content_height
+            .saturating_sub(app.details_height),
+    );
+
+    // trimming would eat the indentation of code blocks and nested lists
+    let details_text = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((app.details_scroll, 0));
+
+    f.render_widget(details_block, rect);
+    f.render_widget(details_text, text_area);
+
+    if app.details_content_height > app.details_height {
+        let scrollbar_area = Rect {
+            x: text_area.x + text_area.width,
+            width: 1,
+            ..text_area
+        };
+
+        render_scrollbar(
+            f,
+            scrollbar_area,
+            app.details_scroll,
+            app.details_content_height,
+            title_style,
+            app.theme.muted,
+        );
+    }
+}
+
+fn render_scrollbar<B: Backend>(
+    f: &mut Frame<B>,
+    rect: Rect,
+    scroll: u16,
+    content_height: u16,
+    thumb_style: Style,
+    track_style: Style,
+) {
+    if rect.height == 0 {
+        return;
+    }
+
+    let track_height = rect.height as u32;
+    let content_height = cmp::max(content_height as u32, 1);
+
+    let thumb_height = cmp::max(track_height * track_height / content_height, 1);
+    let thumb_start = cmp::min(
+        scroll as u32 * track_height / content_height,
+        track_height - thumb_height,
+    );
+
+    let lines: Vec<Line> = (0..track_height)
+        .map(|i| {
+            if i >= thumb_start && i < thumb_start + thumb_height {
+                Line::from(Span::styled("█", thumb_style))
+            } else {
+                Line::from(Span::styled("│", track_style))
+            }
+        })
+        .collect();
 
-    let description_block = details_text.block(details_block);
-    f.render_widget(description_block, rects[1]);
+    f.render_widget(Paragraph::new(lines), rect);
 }
 
-fn render_table<'a, T>(rows: T, header_content: Vec<&'a str>) -> Table<'a>
+// approximates how many rows the lines take once the paragraph wraps them
+fn wrapped_height(lines: &[Line], width: u16) -> u16 {
+    let width = cmp::max(width as usize, 1);
+
+    let height: usize = lines
+        .iter()
+        .map(|line| cmp::max(line.width().div_ceil(width), 1))
+        .sum();
+
+    cmp::min(height, u16::MAX as usize) as u16
+}
+
+// the table area, and where the details go if they are shown at all
+fn split_main_area(app: &Hourglass, rect: Rect) -> (Rect, Option<Rect>) {
+    let direction = match app.layout {
+        LayoutMode::SideBySide => Direction::Horizontal,
+        LayoutMode::Stacked => Direction::Vertical,
+        LayoutMode::Narrow => {
+            let details = (app.focus == Focus::Details).then_some(rect);
+
+            return (rect, details);
+        }
+    };
+
+    let areas = Layout::default()
+        .direction(direction)
+        .constraints([
+            Constraint::Percentage(app.split_percent),
+            Constraint::Percentage(100 - app.split_percent),
+        ])
+        .split(rect);
+
+    (areas[0], Some(areas[1]))
+}
+
+// the table has a bottom border, and a header followed by an empty line
+fn set_table_areas(app: &mut Hourglass, table: Rect, details: Option<Rect>) {
+    app.areas.table_rows = Rect {
+        y: table.y + 2,
+        height: table.height.saturating_sub(3),
+        ..table
+    };
+    app.areas.details = details.unwrap_or_default();
+
+    // the details popup covers the table
+    if app.layout == LayoutMode::Narrow && details.is_some() {
+        app.areas.table_rows = Rect::default();
+    }
+}
+
+fn is_table_focused(app: &Hourglass) -> bool {
+    app.focus == Focus::Table
+}
+
+fn section_title<'a>(title: String, theme: &Theme) -> Line<'a> {
+    Line::from(Span::styled(
+        title,
+        theme.muted.add_modifier(Modifier::BOLD),
+    ))
+}
+
+// the configured percentages, except that the ID and the age are never cut off,
+// and the description gets whatever is left instead of being squashed
+fn column_widths(app: &Hourglass, table: Rect, id_width: u16) -> [Constraint; 3] {
+    let columns = &app.config.columns;
+    // minus the padding, the highlight symbol and the spacing between columns
+    let width = table.width.saturating_sub(8);
+    let percent = |percentage: u16| (width as u32 * percentage as u32 / 100) as u16;
+
+    let id = cmp::max(percent(columns.id), id_width);
+    let age = cmp::max(percent(columns.age), AGE_COLUMN_WIDTH);
+
+    [
+        Constraint::Length(id),
+        Constraint::Length(width.saturating_sub(id + age)),
+        Constraint::Length(age),
+    ]
+}
+
+fn render_table<'a, T>(
+    rows: T,
+    header_content: Vec<&'a str>,
+    widths: &'a [Constraint],
+    is_focused: bool,
+    theme: &Theme,
+) -> Table<'a>
 where
     T: IntoIterator<Item = Row<'a>>,
 {
+    // keep showing the selected row while the details pane is focused, just less loudly
+    let highlight_style = if is_focused {
+        theme.selected_row
+    } else {
+        theme.selected_row_unfocused
+    };
+
     let header_cells = header_content
         .iter()
-        .map(|x| Cell::from(*x).style(Style::default().add_modifier(Modifier::DIM)));
+        .map(|x| Cell::from(*x).style(theme.header));
 
     let header = Row::new(header_cells)
         .style(Style::default())
@@ -330,12 +1039,8 @@ where
                 .padding(Padding::horizontal(1)),
         )
         .highlight_symbol("> ")
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
-        .widths(&[
-            Constraint::Percentage(15),
-            Constraint::Percentage(75),
-            Constraint::Percentage(10),
-        ])
+        .highlight_style(highlight_style)
+        .widths(widths)
 }
 
 // https://github.com/kdheepak/taskwarrior-tui/blob/main/src/app.rs#L890
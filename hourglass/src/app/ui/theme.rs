@@ -0,0 +1,348 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::str::FromStr;
+
+pub const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+/// Every style the UI uses, so a theme can change all of them at once
+#[derive(Clone, Copy, Debug)]
+pub struct Theme {
+    pub tab: Style,
+    pub tab_selected: Style,
+    pub header: Style,
+    pub selected_row: Style,
+    /// the selected row while the details pane has the focus
+    pub selected_row_unfocused: Style,
+    pub completed: Style,
+    pub overdue: Style,
+    /// rows marked for a bulk operation
+    pub marked: Style,
+    pub issue_number: Style,
+    pub issue_label: Style,
+    pub command: Style,
+    pub command_title: Style,
+    pub error: Style,
+    pub info: Style,
+    pub completion_selected: Style,
+    /// title and scrollbar of the focused pane
+    pub focused: Style,
+    /// secondary text: field names, section titles, list bullets...
+    pub muted: Style,
+    pub heading: Style,
+    pub code: Style,
+    pub link: Style,
+    pub quote: Style,
+    pub checkbox: Style,
+}
+
+/// A style as written in the config, e.g. `{ fg = "yellow", modifiers = ["bold"] }`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<String>,
+}
+
+/// A custom theme from the config, styles that are not set come from `base`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<String, StyleSpec>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    /// Looks `name` up in the custom themes first, then in the built-in ones
+    pub fn resolve(name: &str, custom: &BTreeMap<String, ThemeSpec>) -> Result<Theme, String> {
+        let mut theme = match custom.get(name) {
+            Some(spec) => {
+                let base = spec.base.as_deref().unwrap_or("dark");
+                let mut theme = Theme::builtin(base).ok_or_else(|| {
+                    format!(
+                        "theme `{}` is based on unknown theme `{}`, expected one of {}",
+                        name,
+                        base,
+                        BUILTIN_THEMES.join(", ")
+                    )
+                })?;
+
+                for (key, style) in spec.styles.iter() {
+                    let style = parse_style(style)
+                        .map_err(|err| format!("theme `{}`, style `{}`: {}", name, key, err))?;
+
+                    *theme
+                        .style_mut(key)
+                        .ok_or_else(|| format!("theme `{}` has unknown style `{}`", name, key))? =
+                        style;
+                }
+
+                theme
+            }
+            None => Theme::builtin(name).ok_or_else(|| {
+                format!(
+                    "unknown theme `{}`, expected one of {} or a theme from [themes]",
+                    name,
+                    BUILTIN_THEMES.join(", ")
+                )
+            })?,
+        };
+
+        // https://no-color.org
+        if env::var("NO_COLOR").is_ok_and(|value| !value.is_empty()) {
+            theme = theme.without_colors();
+        }
+
+        Ok(theme)
+    }
+
+    fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    fn dark() -> Theme {
+        let dim = Style::default().add_modifier(Modifier::DIM);
+
+        Theme {
+            tab: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::DIM),
+            tab_selected: Style::default()
+                .fg(Color::Yellow)
+                .remove_modifier(Modifier::DIM),
+            header: dim,
+            selected_row: Style::default().add_modifier(Modifier::BOLD),
+            selected_row_unfocused: dim,
+            completed: Style::default().add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
+            overdue: Style::default().fg(Color::Red),
+            marked: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            issue_number: Style::default().fg(Color::Green),
+            issue_label: Style::default().fg(Color::Cyan),
+            command: Style::default(),
+            command_title: Style::default(),
+            error: Style::default().fg(Color::Red),
+            info: dim,
+            completion_selected: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            focused: Style::default().fg(Color::Yellow),
+            muted: dim,
+            heading: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            code: Style::default().fg(Color::LightRed),
+            link: Style::default().fg(Color::Blue),
+            quote: Style::default()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::ITALIC),
+            checkbox: Style::default().fg(Color::Cyan),
+        }
+    }
+
+    // DIM on white backgrounds is barely readable, so use gray instead
+    fn light() -> Theme {
+        let muted = Style::default().fg(Color::DarkGray);
+
+        Theme {
+            tab: muted,
+            tab_selected: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            header: muted,
+            selected_row: Style::default().add_modifier(Modifier::BOLD),
+            selected_row_unfocused: muted,
+            completed: muted.add_modifier(Modifier::CROSSED_OUT),
+            overdue: Style::default().fg(Color::Red),
+            marked: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            issue_number: Style::default().fg(Color::Magenta),
+            issue_label: Style::default().fg(Color::Blue),
+            command: Style::default(),
+            command_title: Style::default(),
+            error: Style::default().fg(Color::Red),
+            info: muted,
+            completion_selected: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            focused: Style::default().fg(Color::Blue),
+            muted,
+            heading: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            code: Style::default().fg(Color::Red),
+            link: Style::default().fg(Color::Blue),
+            quote: muted.add_modifier(Modifier::ITALIC),
+            checkbox: Style::default().fg(Color::Magenta),
+        }
+    }
+
+    // no DIM at all and reversed colors for anything that is selected
+    fn high_contrast() -> Theme {
+        let plain = Style::default().fg(Color::White);
+        let selected = Style::default()
+            .fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+
+        Theme {
+            tab: plain,
+            tab_selected: selected,
+            header: plain.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            selected_row: selected,
+            selected_row_unfocused: Style::default().add_modifier(Modifier::REVERSED),
+            completed: plain.add_modifier(Modifier::CROSSED_OUT),
+            overdue: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            marked: Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+            issue_number: Style::default().fg(Color::LightGreen),
+            issue_label: Style::default().fg(Color::LightCyan),
+            command: plain,
+            command_title: plain.add_modifier(Modifier::BOLD),
+            error: Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+            info: plain,
+            completion_selected: selected,
+            focused: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            muted: plain,
+            heading: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            code: Style::default().fg(Color::LightRed),
+            link: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::UNDERLINED),
+            quote: plain.add_modifier(Modifier::ITALIC),
+            checkbox: Style::default().fg(Color::LightCyan),
+        }
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "tab" => &mut self.tab,
+            "tab_selected" => &mut self.tab_selected,
+            "header" => &mut self.header,
+            "selected_row" => &mut self.selected_row,
+            "selected_row_unfocused" => &mut self.selected_row_unfocused,
+            "completed" => &mut self.completed,
+            "overdue" => &mut self.overdue,
+            "marked" => &mut self.marked,
+            "issue_number" => &mut self.issue_number,
+            "issue_label" => &mut self.issue_label,
+            "command" => &mut self.command,
+            "command_title" => &mut self.command_title,
+            "error" => &mut self.error,
+            "info" => &mut self.info,
+            "completion_selected" => &mut self.completion_selected,
+            "focused" => &mut self.focused,
+            "muted" => &mut self.muted,
+            "heading" => &mut self.heading,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "quote" => &mut self.quote,
+            "checkbox" => &mut self.checkbox,
+            _ => return None,
+        };
+
+        Some(style)
+    }
+
+    // keeps bold, italic... so selected and completed rows still stand out
+    fn without_colors(mut self) -> Theme {
+        for style in [
+            &mut self.tab,
+            &mut self.tab_selected,
+            &mut self.header,
+            &mut self.selected_row,
+            &mut self.selected_row_unfocused,
+            &mut self.completed,
+            &mut self.overdue,
+            &mut self.marked,
+            &mut self.issue_number,
+            &mut self.issue_label,
+            &mut self.command,
+            &mut self.command_title,
+            &mut self.error,
+            &mut self.info,
+            &mut self.completion_selected,
+            &mut self.focused,
+            &mut self.muted,
+            &mut self.heading,
+            &mut self.code,
+            &mut self.link,
+            &mut self.quote,
+            &mut self.checkbox,
+        ] {
+            style.fg = None;
+            style.bg = None;
+        }
+
+        self
+    }
+}
+
+fn parse_style(spec: &StyleSpec) -> Result<Style, String> {
+    let mut style = Style::default();
+
+    if let Some(fg) = &spec.fg {
+        style = style.fg(parse_color(fg)?);
+    }
+
+    if let Some(bg) = &spec.bg {
+        style = style.bg(parse_color(bg)?);
+    }
+
+    for modifier in spec.modifiers.iter() {
+        style = style.add_modifier(parse_modifier(modifier)?);
+    }
+
+    Ok(style)
+}
+
+// names like `lightblue`, a 256 color index or `#rrggbb`
+fn parse_color(color: &str) -> Result<Color, String> {
+    Color::from_str(color).map_err(|_| format!("unknown color `{}`", color))
+}
+
+fn parse_modifier(modifier: &str) -> Result<Modifier, String> {
+    match modifier.to_lowercase().as_str() {
+        "bold" => Ok(Modifier::BOLD),
+        "dim" => Ok(Modifier::DIM),
+        "italic" => Ok(Modifier::ITALIC),
+        "underlined" => Ok(Modifier::UNDERLINED),
+        "reversed" => Ok(Modifier::REVERSED),
+        "crossed_out" => Ok(Modifier::CROSSED_OUT),
+        "slow_blink" => Ok(Modifier::SLOW_BLINK),
+        "rapid_blink" => Ok(Modifier::RAPID_BLINK),
+        "hidden" => Ok(Modifier::HIDDEN),
+        _ => Err(format!("unknown modifier `{}`", modifier)),
+    }
+}
@@ -0,0 +1,196 @@
+use clap::{Parser, Subcommand};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::app::config::{Config, LoadedConfig};
+use crate::app::formats::{ExportOptions, Format, GroupBy, CSV_COLUMNS};
+use crate::app::storage::{self, StorageFormat};
+use crate::app::Hourglass;
+
+/// A todo app in the terminal
+#[derive(Parser)]
+#[command(name = "hourglass", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Print the effective configuration and the files it was loaded from
+    Config,
+    /// Write the tasks to a file, or to stdout without one
+    Export {
+        /// csv, todotxt, taskwarrior, ical or markdown
+        #[arg(short, long, value_parser = parse_format)]
+        format: Format,
+        path: Option<PathBuf>,
+        /// Only the tasks matching a filter, like `:filter`, e.g. "+urgent project:web"
+        #[arg(long)]
+        filter: Option<String>,
+        /// A named list instead of the default one
+        #[arg(long)]
+        list: Option<String>,
+        /// The csv columns, in order, e.g. id,description,due
+        #[arg(long, value_delimiter = ',', value_parser = parse_column)]
+        columns: Vec<String>,
+        /// The sections of the markdown checklist: project, tag or none
+        #[arg(long, default_value = "project", value_parser = parse_group_by)]
+        group_by: GroupBy,
+    },
+    /// Two-way sync with the CalDAV collection set in the config
+    Sync,
+    /// Add the tasks of a file to the task list
+    Import {
+        /// todotxt, taskwarrior, ical or markdown
+        #[arg(short, long, value_parser = parse_format)]
+        format: Format,
+        path: PathBuf,
+        /// A named list instead of the default one
+        #[arg(long)]
+        list: Option<String>,
+    },
+    /// Move the JSON task list and archive into the SQLite database
+    Migrate,
+    /// Merge two versions of a task file field by field, as a git merge driver:
+    /// `hourglass merge %O %A %B`
+    Merge {
+        /// The common ancestor
+        base: PathBuf,
+        /// Our version, replaced by the result
+        ours: PathBuf,
+        /// Their version
+        theirs: PathBuf,
+    },
+}
+
+fn parse_format(s: &str) -> Result<Format, String> {
+    Format::parse(s).ok_or_else(|| {
+        format!(
+            "unknown format `{}`, expected one of {}",
+            s,
+            Format::NAMES.join(", ")
+        )
+    })
+}
+
+fn parse_column(s: &str) -> Result<String, String> {
+    if CSV_COLUMNS.contains(&s) {
+        Ok(s.to_string())
+    } else {
+        Err(format!(
+            "unknown column `{}`, expected one of {}",
+            s,
+            CSV_COLUMNS.join(", ")
+        ))
+    }
+}
+
+fn parse_group_by(s: &str) -> Result<GroupBy, String> {
+    GroupBy::parse(s).ok_or_else(|| {
+        format!(
+            "cannot group by `{}`, expected one of {}",
+            s,
+            GroupBy::NAMES.join(", ")
+        )
+    })
+}
+
+pub fn show_config(loaded: &LoadedConfig) {
+    println!("# loaded from: {}", loaded.sources.join(", "));
+    print!("{}", loaded.config.to_toml());
+}
+
+pub fn export(
+    hourglass: &mut Hourglass,
+    format: Format,
+    path: Option<PathBuf>,
+    filter: Option<String>,
+    list: Option<String>,
+    options: ExportOptions,
+) -> Result<(), String> {
+    if let Some(list) = list {
+        hourglass.select_list(&list)?;
+    }
+
+    let content = match filter {
+        Some(filter) => hourglass.export_matching(format, &filter, &options)?,
+        None => hourglass.export(format, &options),
+    };
+
+    match path {
+        Some(path) => fs::write(&path, content)
+            .map_err(|err| format!("unable to export to {}: {}", path.display(), err)),
+        None => {
+            print!("{}", content);
+
+            Ok(())
+        }
+    }
+}
+
+pub fn sync(hourglass: &mut Hourglass) -> Result<(), String> {
+    let result = hourglass.sync()?;
+
+    for conflict in result.conflicts.iter() {
+        eprintln!("{}", conflict);
+    }
+
+    println!("{}", result.summary());
+
+    Ok(())
+}
+
+pub fn import(
+    hourglass: &mut Hourglass,
+    format: Format,
+    path: PathBuf,
+    list: Option<String>,
+) -> Result<(), String> {
+    if let Some(list) = list {
+        hourglass.select_list(&list)?;
+    }
+
+    let message = hourglass.import(format, &path)?;
+    eprintln!("{}", message);
+
+    Ok(())
+}
+
+// the JSON files are left as they are, they are ignored once the backend is sqlite
+pub fn migrate(hourglass: &Hourglass) -> Result<(), String> {
+    let message = hourglass.migrate()?;
+
+    println!("{}", message);
+    println!("set backend = \"sqlite\" in the config to use it");
+
+    Ok(())
+}
+
+/// Writes the result over `ours` in the format it is in, like git expects. An empty `ours`
+/// gets the configured storage format
+pub fn merge(config: &Config, base: PathBuf, ours: PathBuf, theirs: PathBuf) -> Result<(), String> {
+    let read = |path: &PathBuf| {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("unable to read {}: {}", path.display(), err))?;
+        let tasks = storage::deserialize(&content)
+            .map_err(|err| format!("unable to read {}: {}", path.display(), err))?;
+
+        Ok::<_, String>((content, tasks))
+    };
+
+    let (our_content, our_tasks) = read(&ours)?;
+    let format = StorageFormat::detect(&our_content).unwrap_or(config.storage_format);
+
+    let merged = storage::merge(&read(&base)?.1, &our_tasks, &read(&theirs)?.1)?;
+
+    for conflict in merged.conflicts.iter() {
+        eprintln!("{}", conflict);
+    }
+
+    fs::write(
+        &ours,
+        storage::serialize(&merged.tasks.iter().collect::<Vec<_>>(), format),
+    )
+    .map_err(|err| format!("unable to write {}: {}", ours.display(), err))
+}
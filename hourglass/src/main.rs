@@ -2,23 +2,100 @@
 extern crate lazy_static;
 extern crate dotenv;
 
+use clap::Parser;
 use dotenv::dotenv;
-use std::io;
+use std::fmt::Display;
+use std::{io, process};
 
 mod app;
+mod cli;
 mod util;
 
+use app::config::Config;
+use app::formats::ExportOptions;
+use app::keymap::Keymap;
+use app::storage::Backend;
 use app::Hourglass;
+use cli::{Cli, Commands};
 
 fn main() -> io::Result<()> {
     dotenv().ok();
 
-    let mut hourglass = Hourglass::new();
+    let cli = Cli::parse();
+
+    let loaded = exit_on_error(Config::load());
+
+    if let Some(Commands::Config) = cli.command {
+        cli::show_config(&loaded);
+
+        return Ok(());
+    }
+
+    // git runs the merge driver on files that aren't the task list of the current directory
+    if let Some(Commands::Merge { base, ours, theirs }) = cli.command {
+        exit_on_error(cli::merge(&loaded.config, base, ours, theirs));
+
+        return Ok(());
+    }
+
+    let keymap = exit_on_error(Keymap::load());
+
+    let mut config = loaded.config;
+
+    // migrating reads the JSON files whatever the backend is
+    if let Some(Commands::Migrate) = cli.command {
+        config.backend = Backend::Json;
+    }
+
+    let mut hourglass = Hourglass::new(config, keymap);
     hourglass.load_tasks()?;
 
+    if let Some(command) = cli.command {
+        match command {
+            Commands::Config | Commands::Merge { .. } => {}
+            Commands::Export {
+                format,
+                path,
+                filter,
+                list,
+                columns,
+                group_by,
+            } => {
+                let options = ExportOptions { columns, group_by };
+
+                exit_on_error(cli::export(
+                    &mut hourglass,
+                    format,
+                    path,
+                    filter,
+                    list,
+                    options,
+                ))
+            }
+            Commands::Sync => exit_on_error(cli::sync(&mut hourglass)),
+            Commands::Migrate => exit_on_error(cli::migrate(&hourglass)),
+            Commands::Import { format, path, list } => {
+                exit_on_error(cli::import(&mut hourglass, format, path, list))
+            }
+        }
+
+        return Ok(());
+    }
+
     let mut terminal = Hourglass::start_tui()?;
 
     let r = hourglass.run(&mut terminal);
     Hourglass::pause_tui()?;
     r
 }
+
+// configuration mistakes are reported before the TUI takes over the terminal
+fn exit_on_error<T, E: Display>(result: Result<T, E>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -1,9 +1,14 @@
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use std::env;
+use std::path::PathBuf;
 
 pub mod date_format {
-    use chrono::{DateTime, Utc};
+    use chrono::{DateTime, NaiveDateTime, Utc};
     use serde::{self, Deserialize, Deserializer, Serializer};
 
+    /// Taskwarrior's timestamps, e.g. `20230528T142500Z`
+    pub const COMPACT_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
     // The signature of a serialize_with function must follow the pattern:
     //
     //    fn serialize<S>(&T, S) -> Result<S::Ok, S::Error>
@@ -33,10 +38,83 @@ pub mod date_format {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        s.parse::<DateTime<Utc>>().map_err(serde::de::Error::custom)
+        parse(&s).map_err(serde::de::Error::custom)
         // Utc.datetime_from_str(&s, FORMAT)
         //     .map_err(serde::de::Error::custom)
     }
+
+    /// Reads a RFC3339 date or a compact one, whichever the file has
+    pub fn parse(s: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+        s.parse::<DateTime<Utc>>().or_else(|err| {
+            NaiveDateTime::parse_from_str(s, COMPACT_FORMAT)
+                .map(|date| date.and_utc())
+                .map_err(|_| err)
+        })
+    }
+
+    /// Writes dates as `20230528T142500Z`, the way Taskwarrior does, and reads both formats
+    pub mod compact {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(&date.format(super::COMPACT_FORMAT).to_string())
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            super::deserialize(deserializer)
+        }
+
+        /// The same for optional dates, use it with `#[serde(default)]`
+        pub mod option {
+            use chrono::{DateTime, Utc};
+            use serde::{Deserialize, Deserializer, Serializer};
+
+            pub fn serialize<S>(
+                date: &Option<DateTime<Utc>>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                match date {
+                    Some(date) => super::serialize(date, serializer),
+                    None => serializer.serialize_none(),
+                }
+            }
+
+            pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Option::<String>::deserialize(deserializer)?
+                    .map(|s| super::super::parse(&s).map_err(serde::de::Error::custom))
+                    .transpose()
+            }
+        }
+    }
+}
+
+/// $XDG_CONFIG_HOME when set, the platform's config directory otherwise
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(dirs::config_dir)
+}
+
+/// $XDG_DATA_HOME when set, the platform's data directory otherwise
+pub fn data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(dirs::data_dir)
 }
 
 pub fn format_time(from_dt: DateTime<Utc>, to_dt: DateTime<Utc>) -> String {
@@ -71,3 +149,131 @@ pub fn convert_utc_to_local(utc_time: DateTime<Utc>, time_format: &str) -> Strin
 
     local_time.format(time_format).to_string()
 }
+
+/// Words understood by `parse_date`, also offered by the command bar completion
+pub const DATE_KEYWORDS: [&str; 14] = [
+    "today",
+    "tomorrow",
+    "yesterday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "eow",
+    "eom",
+    "eoy",
+    "someday",
+];
+
+/// Parses a date keyword (`tomorrow`, `friday`, `eom`...) or a `YYYY-MM-DD` date relative to `now`.
+/// Dates are set to the end of the day in local time
+pub fn parse_date(input: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let today = now.date_naive();
+
+    let date = match input.to_lowercase().as_str() {
+        "today" => today,
+        "tomorrow" => today.succ_opt()?,
+        "yesterday" => today.pred_opt()?,
+        "eow" => match today.weekday() {
+            Weekday::Sun => today,
+            _ => next_weekday(today, Weekday::Sun),
+        },
+        "eom" => {
+            let (year, month) = if today.month() == 12 {
+                (today.year() + 1, 1)
+            } else {
+                (today.year(), today.month() + 1)
+            };
+
+            NaiveDate::from_ymd_opt(year, month, 1)?.pred_opt()?
+        }
+        "eoy" => NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+        // far enough in the future to never show up as due
+        "someday" => NaiveDate::from_ymd_opt(9999, 12, 30)?,
+        other => match other.parse::<Weekday>() {
+            Ok(weekday) => next_weekday(today, weekday),
+            Err(_) => NaiveDate::parse_from_str(other, "%Y-%m-%d").ok()?,
+        },
+    };
+
+    let end_of_day = date.and_time(NaiveTime::from_hms_opt(23, 59, 59)?);
+
+    Local
+        .from_local_datetime(&end_of_day)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+// the first day after `from` that falls on `weekday`
+fn next_weekday(from: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days_ahead = (7 + weekday.num_days_from_monday() as i64
+        - from.weekday().num_days_from_monday() as i64)
+        % 7;
+
+    let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+
+    from + Duration::days(days_ahead)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a Wednesday
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2023, 6, 14, 10, 30, 0).unwrap()
+    }
+
+    fn date(input: &str) -> Option<String> {
+        parse_date(input, now()).map(|date| {
+            let date: DateTime<Local> = DateTime::from(date);
+
+            date.format("%Y-%m-%d %H:%M:%S").to_string()
+        })
+    }
+
+    #[test]
+    fn relative_days() {
+        assert_eq!(date("today").as_deref(), Some("2023-06-14 23:59:59"));
+        assert_eq!(date("Tomorrow").as_deref(), Some("2023-06-15 23:59:59"));
+        assert_eq!(date("yesterday").as_deref(), Some("2023-06-13 23:59:59"));
+    }
+
+    #[test]
+    fn weekdays_are_in_the_future() {
+        assert_eq!(date("friday").as_deref(), Some("2023-06-16 23:59:59"));
+        assert_eq!(date("monday").as_deref(), Some("2023-06-19 23:59:59"));
+        // a week from now, not today
+        assert_eq!(date("wednesday").as_deref(), Some("2023-06-21 23:59:59"));
+    }
+
+    #[test]
+    fn ends_of_periods() {
+        assert_eq!(date("eow").as_deref(), Some("2023-06-18 23:59:59"));
+        assert_eq!(date("eom").as_deref(), Some("2023-06-30 23:59:59"));
+        assert_eq!(date("eoy").as_deref(), Some("2023-12-31 23:59:59"));
+        assert_eq!(date("someday").as_deref(), Some("9999-12-30 23:59:59"));
+    }
+
+    #[test]
+    fn end_of_december() {
+        let now = Local.with_ymd_and_hms(2023, 12, 5, 8, 0, 0).unwrap();
+        let eom: DateTime<Local> = DateTime::from(parse_date("eom", now).unwrap());
+
+        assert_eq!(
+            eom.date_naive(),
+            NaiveDate::from_ymd_opt(2023, 12, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date("2024-02-29").as_deref(), Some("2024-02-29 23:59:59"));
+        assert_eq!(date("2023-02-29"), None);
+        assert_eq!(date("next week"), None);
+        assert_eq!(date(""), None);
+    }
+}